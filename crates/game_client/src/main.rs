@@ -1,7 +1,14 @@
-use game_protocol::{connection::GameClient, Message, PlayerAction, WorldSnapshot};
-use game_renderer::{BlockInstance, Camera, InputHandler, Renderer};
-use std::sync::{Arc, Mutex};
+use game_core::objects::grids::block_transform;
+use game_core::objects::physics::{cell_size, FloatOrientation, IntOrientation, IntPosition};
+use game_protocol::{connection::GameClient, Message, PlayerAction, ShipState, WorldSnapshot};
+use game_renderer::{
+    BlockInstance, Camera, EntityInstance, InputHandler, Renderer, RendererConfig, RendererError,
+};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, RwLock};
 use std::thread;
+use tokio::sync::mpsc as async_mpsc;
 use winit::{
     application::ApplicationHandler,
     event::{DeviceEvent, KeyEvent, WindowEvent},
@@ -10,28 +17,121 @@ use winit::{
     window::Window,
 };
 
-/// Thread-safe game state shared between main thread and network thread
-#[derive(Debug, Clone)]
-struct SharedGameState {
-    pub player_position: game_core::objects::Position,
-    pub pending_actions: Vec<PlayerAction>,
-    pub world_state: Option<WorldSnapshot>,
+/// Server state as seen by the render thread, rebuilt by the network thread and swapped whole
+#[derive(Debug, Clone, Default)]
+struct WorldView {
+    pub world_state: WorldSnapshot,
     pub player_id: Option<u32>,
-    pub network_connected: bool,
+    /// Recent timestamped positions of remote players, oldest first
+    pub remote_history: HashMap<u32, VecDeque<(std::time::Instant, glam::Vec3)>>,
 }
 
-impl Default for SharedGameState {
-    fn default() -> Self {
-        Self {
-            player_position: game_core::objects::Position::new(0.0, 0.0, 0.0),
-            pending_actions: Vec::new(),
-            world_state: None,
-            player_id: None,
-            network_connected: false,
-        }
+/// Thread-safe game state shared between main thread and network thread.
+/// Writers only swap the `Arc`, so readers never wait on message handling.
+#[derive(Debug, Default)]
+struct SharedGameState {
+    pub world: RwLock<Option<Arc<WorldView>>>,
+    pub network_connected: AtomicBool,
+}
+
+impl SharedGameState {
+    /// Replaces the world view seen by readers in one swap
+    fn publish(&self, view: &WorldView) {
+        *self.world.write().unwrap() = Some(Arc::new(view.clone()));
+    }
+
+    /// Latest published world view; the lock is held only to clone the `Arc`
+    fn world_view(&self) -> Option<Arc<WorldView>> {
+        self.world.read().unwrap().clone()
     }
 }
 
+/// Render thread -> network thread
+#[derive(Debug)]
+enum ClientInput {
+    Position(glam::Vec3),
+    Action(PlayerAction),
+}
+
+/// Client-side prediction, owned by the network thread
+#[derive(Debug, Default)]
+struct Prediction {
+    pub input_seq: u32,
+    /// Position updates not yet acknowledged by the server
+    pub pending_inputs: VecDeque<(u32, glam::Vec3)>,
+    pub last_sent_position: Option<glam::Vec3>,
+}
+
+/// Remote players are drawn this far in the past so there are two snapshots to blend
+const INTERPOLATION_DELAY: std::time::Duration = std::time::Duration::from_millis(100);
+/// Samples kept per remote player (covers the delay at the 20 Hz server tick)
+const REMOTE_HISTORY_LEN: usize = 5;
+
+/// Position at `at`, linearly blended between the samples around it (clamped at both ends)
+fn interpolate_position(
+    history: &VecDeque<(std::time::Instant, glam::Vec3)>,
+    at: std::time::Instant,
+) -> Option<glam::Vec3> {
+    let (&(first_t, first_p), &(last_t, last_p)) = (history.front()?, history.back()?);
+    if at <= first_t {
+        return Some(first_p);
+    }
+    if at >= last_t {
+        return Some(last_p);
+    }
+    history
+        .iter()
+        .zip(history.iter().skip(1))
+        .find(|(_, (t1, _))| *t1 >= at)
+        .map(|((t0, p0), (t1, p1))| {
+            let span = t1.duration_since(*t0).as_secs_f32();
+            let alpha = if span > 0.0 {
+                at.duration_since(*t0).as_secs_f32() / span
+            } else {
+                1.0
+            };
+            p0.lerp(*p1, alpha)
+        })
+}
+
+/// Distance above which the predicted position is snapped to the server's
+const RECONCILE_THRESHOLD: f32 = 0.5;
+
+/// Authoritative position replayed with the movement the server hasn't processed yet
+fn reconcile(
+    server_position: glam::Vec3,
+    pending_inputs: &VecDeque<(u32, glam::Vec3)>,
+) -> glam::Vec3 {
+    pending_inputs
+        .iter()
+        .fold(server_position, |pos, (_, delta)| pos + *delta)
+}
+
+/// Render instances of a ship's blocks, placed with the ship's position and orientation
+fn ship_block_instances(ship: &ShipState) -> impl Iterator<Item = BlockInstance> + '_ {
+    let (p, o) = (ship.position, ship.orientation);
+    let ship_position = glam::Vec3::new(p.x, p.y, p.z);
+    let ship_rotation = FloatOrientation::new(o.pitch, o.yaw, o.roll).to_quat();
+    let cell = cell_size(ship.is_large);
+    ship.blocks.iter().map(move |block| {
+        let (p, o) = (block.position, block.orientation);
+        BlockInstance {
+            id: block.id,
+            version: ship.version,
+            transform: block_transform(
+                ship_position,
+                ship_rotation,
+                cell,
+                &IntPosition::new(p.x, p.y, p.z),
+                &IntOrientation::new(o.pitch, o.yaw, o.roll),
+            ),
+            block_type: block.block_type.clone(),
+            texture_path: format!("assets/textures/large_grids/{}.png", block.block_type),
+            faction_id: block.faction_id,
+        }
+    })
+}
+
 struct GameApp {
     renderer: Option<Renderer>,
     camera: Camera,
@@ -43,15 +143,27 @@ struct GameApp {
     mouse_captured: bool,
     is_fullscreen: bool,
     // Shared game state (thread-safe)
-    shared_state: Arc<Mutex<SharedGameState>>,
+    shared_state: Arc<SharedGameState>,
+    input_tx: async_mpsc::UnboundedSender<ClientInput>,
+    /// Offsets to apply to the local camera after reconciliation
+    corrections_rx: mpsc::Receiver<glam::Vec3>,
+    /// Network-side channel ends, handed over when networking starts
+    network_channels: Option<(
+        async_mpsc::UnboundedReceiver<ClientInput>,
+        mpsc::Sender<glam::Vec3>,
+    )>,
     // Network throttling for local updates
     last_network_sync: std::time::Instant,
     network_sync_interval: std::time::Duration,
+    /// Set when the window or renderer could not start; reported after the loop exits
+    startup_error: Option<String>,
 }
 
 impl GameApp {
     fn new() -> Self {
         let now = std::time::Instant::now();
+        let (input_tx, input_rx) = async_mpsc::unbounded_channel();
+        let (corrections_tx, corrections_rx) = mpsc::channel();
         Self {
             renderer: None,
             camera: Camera::new(800.0, 600.0),
@@ -62,26 +174,44 @@ impl GameApp {
             current_fps: 0.0,
             mouse_captured: false,
             is_fullscreen: false,
-            shared_state: Arc::new(Mutex::new(SharedGameState::default())),
+            shared_state: Arc::new(SharedGameState::default()),
+            input_tx,
+            corrections_rx,
+            network_channels: Some((input_rx, corrections_tx)),
             last_network_sync: now,
             network_sync_interval: std::time::Duration::from_millis(16), // ~60 FPS network sync
+            startup_error: None,
         }
     }
 
     fn start_networking(&mut self) {
-        println!("Space Engineers Clone - Starting networking thread");
+        tracing::info!("Starting networking thread");
 
         let shared_state = Arc::clone(&self.shared_state);
+        let Some((input_rx, corrections_tx)) = self.network_channels.take() else {
+            return;
+        };
 
         // Spawn networking thread with tokio runtime
         thread::spawn(move || {
             let rt = tokio::runtime::Runtime::new().unwrap();
             rt.block_on(async {
-                run_network_client(shared_state).await;
+                run_network_client(shared_state, input_rx, corrections_tx).await;
             });
         });
     }
 
+    fn handle_resize(&mut self, physical_size: winit::dpi::PhysicalSize<u32>) {
+        if let Some(ref mut renderer) = self.renderer {
+            renderer.resize(physical_size);
+            // Keep the last valid aspect while minimized (0 height would give NaN)
+            if !renderer.is_minimized() {
+                self.camera
+                    .update_aspect(physical_size.width as f32, physical_size.height as f32);
+            }
+        }
+    }
+
     fn update(&mut self) {
         let t1 = std::time::Instant::now();
         let dt = t1.duration_since(self.last_frame_time).as_secs_f32();
@@ -99,66 +229,73 @@ impl GameApp {
         // Update camera with FPS controls
         self.input_handler.update_camera(&mut self.camera, dt);
 
-        // Update shared state with current player position (non-blocking)
+        // Send the current player position to the network thread (never blocks)
         if t1.duration_since(self.last_network_sync) >= self.network_sync_interval {
-            if let Ok(mut state) = self.shared_state.try_lock() {
-                state.player_position = game_core::objects::Position::new(
-                    self.camera.position.x,
-                    self.camera.position.y,
-                    self.camera.position.z,
-                );
+            while let Ok(correction) = self.corrections_rx.try_recv() {
+                self.camera.position += correction;
             }
+            let _ = self
+                .input_tx
+                .send(ClientInput::Position(self.camera.position));
             self.last_network_sync = t1;
         }
 
         let t2 = std::time::Instant::now();
         let d1 = t2.duration_since(t1).as_millis();
-        // Get latest world state from shared data (non-blocking)
-        let should_update = if let Ok(state) = self.shared_state.try_lock() {
-            state.world_state.is_some()
-        } else {
-            false
-        };
+        // Latest world view, read once for the whole frame
+        let view = self.shared_state.world_view();
 
         let t3 = std::time::Instant::now();
         let d2 = t3.duration_since(t2).as_millis();
 
-        if should_update {
-            if let Ok(state) = self.shared_state.try_lock() {
-                if let Some(ref world_state) = state.world_state {
-                    if let Some(ref mut renderer) = self.renderer {
-                        let mut blocks = Vec::new();
-
-                        // Convert all ships' blocks to BlockInstances
-                        for (_ship_id, ship) in &world_state.ships {
-                            let ship_pos =
-                                glam::Vec3::new(ship.position.x, ship.position.y, ship.position.z);
-
-                            for block in &ship.blocks {
-                                // Calculate world position: ship position + block relative position (2.5m per block for large grids)
-                                let block_world_pos = ship_pos
-                                    + glam::Vec3::new(
-                                        block.position.x as f32 * 2.5,
-                                        block.position.y as f32 * 2.5,
-                                        block.position.z as f32 * 2.5,
-                                    );
-
-                                blocks.push(BlockInstance {
-                                    id: block.id,
-                                    version: ship.version,
-                                    position: block_world_pos,
-                                    texture_path: format!(
-                                        "assets/textures/large_grids/{}.png",
-                                        block.block_type
-                                    ),
-                                });
-                            }
-                        }
-
-                        renderer.set_blocks_to_render(blocks);
-                    }
-                }
-            }
+        if let (Some(view), Some(renderer)) = (&view, self.renderer.as_mut()) {
+            let world_state = &view.world_state;
+
+            // Convert all ships' blocks to BlockInstances
+            let blocks: Vec<BlockInstance> = world_state
+                .ships
+                .values()
+                .flat_map(ship_block_instances)
+                .collect();
+
+            renderer.set_blocks_to_render(blocks);
+
+            // Remote players as simple boxes (the local player is the camera),
+            // interpolated between snapshots to hide the network tick
+            let render_time = t3.checked_sub(INTERPOLATION_DELAY).unwrap_or(t3);
+            let remote_players: Vec<(u32, glam::Vec3, &str)> = world_state
+                .players
+                .iter()
+                .filter(|(&id, _)| Some(id) != view.player_id)
+                .map(|(&id, p)| {
+                    let position = view
+                        .remote_history
+                        .get(&id)
+                        .and_then(|h| interpolate_position(h, render_time))
+                        .unwrap_or(glam::Vec3::new(p.position.x, p.position.y, p.position.z));
+                    (id, position, p.name.as_str())
+                })
+                .collect();
+
+            let entities = remote_players
+                .iter()
+                .map(|&(id, position, _)| EntityInstance {
+                    id,
+                    position,
+                    scale: glam::Vec3::new(0.8, 1.8, 0.8),
+                    color: [1.0, 0.55, 0.1, 1.0],
+                })
+                .collect();
+            renderer.set_entities_to_render(entities);
+
+            // Name plates above remote players
+            let labels: Vec<(glam::Vec3, String)> = remote_players
+                .iter()
+                .map(|&(_, position, name)| {
+                    (position + glam::Vec3::new(0.0, 1.3, 0.0), name.to_string())
+                })
+                .collect();
+            renderer.set_labels(&labels);
         }
 
         let t4 = std::time::Instant::now();
@@ -166,33 +303,40 @@ impl GameApp {
 
         // Update overlay data in renderer
         if let Some(ref mut renderer) = self.renderer {
-            let player_pos = if let Ok(state) = self.shared_state.try_lock() {
-                if let (Some(ref world_state), Some(player_id)) =
-                    (&state.world_state, state.player_id)
-                {
-                    world_state
-                        .players
-                        .get(&player_id)
-                        .map(|p| glam::Vec3::new(p.position.x, p.position.y, p.position.z))
-                } else {
-                    None
-                }
-            } else {
-                None
-            };
+            let player_pos = view.as_ref().and_then(|view| {
+                view.world_state
+                    .players
+                    .get(&view.player_id?)
+                    .map(|p| glam::Vec3::new(p.position.x, p.position.y, p.position.z))
+            });
 
             renderer.update_overlay_data(self.current_fps, player_pos);
+
+            // Highlight the block under the crosshair
+            let target = renderer.pick_block(self.camera.position, self.camera.forward(), 50.0);
+            renderer.set_selection(target);
         }
         let t5 = std::time::Instant::now();
         let d4 = t5.duration_since(t4).as_millis();
 
-        // println!("Update timings: get_state={}ms, process_state={}ms, update_overlay={}ms", d2, d3, d4);
+        tracing::trace!(
+            sync_ms = d1,
+            get_state_ms = d2,
+            process_state_ms = d3,
+            update_overlay_ms = d4,
+            "update timings"
+        );
+    }
+
+    /// Logs a fatal startup error and leaves the event loop cleanly
+    fn fail_startup(&mut self, event_loop: &ActiveEventLoop, message: String) {
+        tracing::error!("{message}");
+        self.startup_error = Some(message);
+        event_loop.exit();
     }
 
     fn send_action(&self, action: PlayerAction) {
-        if let Ok(mut state) = self.shared_state.try_lock() {
-            state.pending_actions.push(action);
-        }
+        let _ = self.input_tx.send(ClientInput::Action(action));
     }
 }
 
@@ -202,11 +346,35 @@ impl ApplicationHandler for GameApp {
             .with_title("Space Engineers Clone")
             .with_inner_size(winit::dpi::LogicalSize::new(800.0, 600.0));
 
-        let window = event_loop.create_window(window_attributes).unwrap();
-        let window = Arc::new(window);
+        let window = match event_loop.create_window(window_attributes) {
+            Ok(window) => Arc::new(window),
+            Err(e) => {
+                self.fail_startup(event_loop, format!("Could not create a window: {e}"));
+                return;
+            }
+        };
 
         // Create renderer
-        let renderer = pollster::block_on(Renderer::new(window.clone())).unwrap();
+        let mut renderer = match create_renderer(window) {
+            Ok(renderer) => renderer,
+            Err(e) => {
+                self.fail_startup(event_loop, format!("Could not start the renderer: {e}"));
+                return;
+            }
+        };
+        renderer.set_block_colors(
+            [
+                ("light_armor_block".to_string(), [0.75, 0.75, 0.78, 1.0]),
+                ("heavy_armor_block".to_string(), [0.45, 0.47, 0.5, 1.0]),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        renderer.set_faction_colors(
+            [(1, [0.55, 0.75, 1.0, 1.0]), (2, [1.0, 0.55, 0.5, 1.0])]
+                .into_iter()
+                .collect(),
+        );
         self.renderer = Some(renderer);
 
         // Start networking thread
@@ -232,14 +400,16 @@ impl ApplicationHandler for GameApp {
     ) {
         match event {
             WindowEvent::CloseRequested => {
-                println!("Space Engineers Clone shutting down...");
+                tracing::info!("Shutting down");
                 event_loop.exit();
             }
-            WindowEvent::Resized(physical_size) => {
-                if let Some(ref mut renderer) = self.renderer {
-                    renderer.resize(physical_size);
-                    self.camera
-                        .update_aspect(physical_size.width as f32, physical_size.height as f32);
+            WindowEvent::Resized(physical_size) => self.handle_resize(physical_size),
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                // Moving to a monitor with another DPI changes the physical size
+                // without always sending a Resized first
+                if let Some(size) = self.renderer.as_ref().map(|r| r.window().inner_size()) {
+                    tracing::debug!(scale_factor, ?size, "Scale factor changed");
+                    self.handle_resize(size);
                 }
             }
             WindowEvent::KeyboardInput {
@@ -276,12 +446,61 @@ impl ApplicationHandler for GameApp {
                         KeyCode::KeyF => {
                             // Place block action
                             self.send_action(PlayerAction::SpawnShip);
-                            println!("Placing block...");
+                            tracing::debug!("Placing block");
                         }
                         KeyCode::KeyG => {
                             // Remove block action / Spawn ship
                             self.send_action(PlayerAction::SpawnShip);
-                            println!("Spawning ship...");
+                            tracing::debug!("Spawning ship");
+                        }
+                        KeyCode::F1 => {
+                            if let Some(ref mut renderer) = self.renderer {
+                                renderer.toggle_stats();
+                            }
+                        }
+                        KeyCode::F3 => {
+                            if let Some(ref mut renderer) = self.renderer {
+                                renderer.toggle_frame_graph();
+                            }
+                        }
+                        KeyCode::F4 => {
+                            if let Some(ref mut renderer) = self.renderer {
+                                let fullbright = renderer.toggle_fullbright();
+                                tracing::info!(fullbright, "Lighting mode changed");
+                            }
+                        }
+                        KeyCode::BracketLeft | KeyCode::BracketRight => {
+                            if let Some(ref mut renderer) = self.renderer {
+                                let step = if key_code == KeyCode::BracketRight {
+                                    0.1
+                                } else {
+                                    -0.1
+                                };
+                                let brightness =
+                                    renderer.set_brightness(renderer.brightness() + step);
+                                tracing::info!(brightness, "Brightness changed");
+                            }
+                        }
+                        KeyCode::KeyV => {
+                            if let Some(ref mut renderer) = self.renderer {
+                                let vsync = renderer.present_mode() != wgpu::PresentMode::Fifo;
+                                let applied = renderer.set_vsync(vsync, true);
+                                if vsync != (applied == wgpu::PresentMode::Fifo) {
+                                    tracing::warn!(
+                                        ?applied,
+                                        "Requested present mode unsupported, fell back"
+                                    );
+                                }
+                                tracing::info!(?applied, "Present mode changed");
+                            }
+                        }
+                        KeyCode::Equal | KeyCode::NumpadAdd => {
+                            self.camera.set_render_distance(self.camera.far * 1.5);
+                            tracing::info!(distance = self.camera.far, "Render distance changed");
+                        }
+                        KeyCode::Minus | KeyCode::NumpadSubtract => {
+                            self.camera.set_render_distance(self.camera.far / 1.5);
+                            tracing::info!(distance = self.camera.far, "Render distance changed");
                         }
                         KeyCode::F11 => {
                             // Toggle fullscreen
@@ -294,7 +513,10 @@ impl ApplicationHandler for GameApp {
                                 } else {
                                     renderer.window().set_fullscreen(None);
                                 }
-                                println!("Fullscreen: {}", self.is_fullscreen);
+                                tracing::debug!(
+                                    fullscreen = self.is_fullscreen,
+                                    "Fullscreen toggled"
+                                );
                             }
                         }
                         _ => {}
@@ -312,7 +534,7 @@ impl ApplicationHandler for GameApp {
                         Ok(_) => {}
                         Err(wgpu::SurfaceError::Lost) => renderer.resize(renderer.size()),
                         Err(wgpu::SurfaceError::OutOfMemory) => event_loop.exit(),
-                        Err(e) => eprintln!("Render error: {:?}", e),
+                        Err(e) => tracing::warn!("Render error: {:?}", e),
                     }
                 }
             }
@@ -326,13 +548,10 @@ impl ApplicationHandler for GameApp {
         _device_id: winit::event::DeviceId,
         event: DeviceEvent,
     ) {
-        match event {
-            DeviceEvent::MouseMotion { delta } => {
-                if self.mouse_captured {
-                    self.input_handler.process_mouse(delta);
-                }
+        if let DeviceEvent::MouseMotion { delta } = event {
+            if self.mouse_captured {
+                self.input_handler.process_mouse(delta);
             }
-            _ => {}
         }
     }
 
@@ -343,22 +562,60 @@ impl ApplicationHandler for GameApp {
     }
 }
 
-async fn run_network_client(shared_state: Arc<Mutex<SharedGameState>>) {
-    println!("Network thread started");
+/// Renderer on the configured adapter, or on a software adapter if that fails
+fn create_renderer(window: Arc<Window>) -> Result<Renderer, RendererError> {
+    let config = RendererConfig::from_env();
+    let renderer =
+        match pollster::block_on(Renderer::new_with_config(window.clone(), config.clone())) {
+            Ok(renderer) => renderer,
+            // A fallback adapter can't help if the window has no surface
+            Err(e @ RendererError::Surface(_)) => return Err(e),
+            Err(e) => {
+                tracing::warn!(error = %e, "GPU renderer unavailable, trying a software adapter");
+                let fallback = RendererConfig {
+                    force_adapter_name: None,
+                    force_fallback_adapter: true,
+                    ..config.clone()
+                };
+                pollster::block_on(Renderer::new_with_config(window, fallback))?
+            }
+        };
+
+    let adapter = renderer.adapter_info();
+    tracing::info!(
+        name = %adapter.name,
+        backend = ?adapter.backend,
+        device_type = ?adapter.device_type,
+        "Using GPU adapter"
+    );
+    if let Some(ref wanted) = config.force_adapter_name {
+        if !adapter.name.to_lowercase().contains(&wanted.to_lowercase()) {
+            tracing::warn!(%wanted, "Requested adapter not found, using default selection");
+        }
+    }
+    Ok(renderer)
+}
+
+async fn run_network_client(
+    shared_state: Arc<SharedGameState>,
+    mut input_rx: async_mpsc::UnboundedReceiver<ClientInput>,
+    corrections_tx: mpsc::Sender<glam::Vec3>,
+) {
+    tracing::info!("Network thread started");
 
     // Connect to server
     let mut client = GameClient::new();
     loop {
         match client.connect("127.0.0.1:8080").await {
             Ok(()) => {
-                println!("Connected to server");
-                if let Ok(mut state) = shared_state.lock() {
-                    state.network_connected = true;
-                }
+                tracing::info!("Connected to server");
+                shared_state
+                    .network_connected
+                    .store(true, Ordering::Relaxed);
                 break;
             }
             Err(_) => {
-                println!("Retrying connection...");
+                tracing::warn!("Connection failed, retrying in 2s");
                 tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
                 client = GameClient::new();
             }
@@ -371,83 +628,342 @@ async fn run_network_client(shared_state: Arc<Mutex<SharedGameState>>) {
     });
 
     // Get sender and receiver
-    let ws_tx = client.ws_tx.clone();
+    let Some(tx) = client.ws_tx.clone() else {
+        return;
+    };
     let mut client_rx = client.message_rx;
 
-    // Spawn task to handle server messages
-    let shared_state_msgs = Arc::clone(&shared_state);
-    tokio::spawn(async move {
-        while let Some(message) = client_rx.recv().await {
-            handle_network_message(&shared_state_msgs, message).await;
-        }
-    });
-
-    // Main network loop: send pending actions and position updates
-    if let Some(tx) = ws_tx {
-        let mut last_position_send = std::time::Instant::now();
-        let position_send_interval = std::time::Duration::from_millis(16); // ~60 FPS
-
-        loop {
-            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    // Server messages, render-thread inputs and position updates on one task,
+    // so the prediction and world view need no lock
+    let mut view = WorldView::default();
+    let mut prediction = Prediction::default();
+    let mut player_position = None;
+    let mut position_tick = tokio::time::interval(std::time::Duration::from_millis(16)); // ~60 FPS
 
-            if let Ok(mut state) = shared_state.try_lock() {
-                // Send pending actions
-                for action in state.pending_actions.drain(..) {
-                    let msg = Message::PlayerAction { action };
-                    let _ = tx.send(msg);
+    loop {
+        tokio::select! {
+            Some(message) = client_rx.recv() => {
+                if handle_network_message(&mut view, &mut prediction, &corrections_tx, message) {
+                    shared_state.publish(&view);
                 }
-
-                // Send position updates periodically
-                let now = std::time::Instant::now();
-                if now.duration_since(last_position_send) >= position_send_interval {
-                    let position_msg = Message::PlayerAction {
-                        action: PlayerAction::UpdatePosition {
-                            position: state.player_position.clone(),
-                        },
-                    };
-                    let _ = tx.send(position_msg);
-                    last_position_send = now;
+            }
+            Some(input) = input_rx.recv() => match input {
+                ClientInput::Action(action) => {
+                    let _ = tx.send(Message::PlayerAction { action });
                 }
+                ClientInput::Position(position) => player_position = Some(position),
+            },
+            _ = position_tick.tick() => {
+                let Some(position) = player_position else {
+                    continue;
+                };
+                let delta = position - prediction.last_sent_position.unwrap_or(position);
+                prediction.input_seq += 1;
+                let seq = prediction.input_seq;
+                prediction.pending_inputs.push_back((seq, delta));
+                prediction.last_sent_position = Some(position);
+
+                let position_msg = Message::PlayerAction {
+                    action: PlayerAction::UpdatePosition {
+                        position: game_protocol::Position::new(position.x, position.y, position.z),
+                        seq,
+                    },
+                };
+                let _ = tx.send(position_msg);
             }
         }
     }
 }
 
-async fn handle_network_message(shared_state: &Arc<Mutex<SharedGameState>>, message: Message) {
-    if let Ok(mut state) = shared_state.lock() {
-        match message {
-            Message::Welcome {
-                player_id,
-                world_state,
-            } => {
-                println!(
-                    "Welcome! Player {} in world with {} ships",
-                    player_id,
-                    world_state.ships.len()
-                );
-                state.player_id = Some(player_id);
-                state.world_state = Some(world_state);
-            }
-            Message::WorldSnapshot { snapshot } => {
-                // Update world state with new snapshot
-                state.world_state = Some(snapshot);
-            }
-            Message::Error { message } => {
-                println!("Server error: {}", message);
+/// Applies a server message; returns true if the world view changed
+fn handle_network_message(
+    view: &mut WorldView,
+    prediction: &mut Prediction,
+    corrections_tx: &mpsc::Sender<glam::Vec3>,
+    message: Message,
+) -> bool {
+    match message {
+        Message::Welcome {
+            player_id,
+            world_state,
+        } => {
+            tracing::info!(player_id, ships = world_state.ships.len(), "Joined world");
+            view.player_id = Some(player_id);
+            view.world_state = world_state;
+            true
+        }
+        Message::WorldSnapshot { snapshot } => {
+            if let Some(player_id) = view.player_id {
+                if let Some(me) = snapshot.players.get(&player_id) {
+                    reconcile_local_player(prediction, corrections_tx, me);
+                }
             }
-            _ => {}
+            record_remote_positions(view, &snapshot);
+            // Update world state with new snapshot
+            view.world_state = snapshot;
+            true
+        }
+        Message::Error { message } => {
+            tracing::error!("Server error: {}", message);
+            false
         }
+        _ => false,
+    }
+}
+
+/// Appends each remote player's position to its interpolation history
+fn record_remote_positions(view: &mut WorldView, snapshot: &WorldSnapshot) {
+    let now = std::time::Instant::now();
+    let local_id = view.player_id;
+    view.remote_history
+        .retain(|id, _| snapshot.players.contains_key(id));
+    for (&id, p) in &snapshot.players {
+        if Some(id) == local_id {
+            continue;
+        }
+        let history = view.remote_history.entry(id).or_default();
+        history.push_back((
+            now,
+            glam::Vec3::new(p.position.x, p.position.y, p.position.z),
+        ));
+        while history.len() > REMOTE_HISTORY_LEN {
+            history.pop_front();
+        }
+    }
+}
+
+/// Drops acknowledged inputs and sends a camera correction if the server disagrees
+fn reconcile_local_player(
+    prediction: &mut Prediction,
+    corrections_tx: &mpsc::Sender<glam::Vec3>,
+    me: &game_protocol::PlayerState,
+) {
+    // 0 = the server hasn't processed any of our updates yet
+    if me.last_input_seq == 0 {
+        return;
+    }
+    prediction
+        .pending_inputs
+        .retain(|(seq, _)| *seq > me.last_input_seq);
+    let Some(last_sent) = prediction.last_sent_position else {
+        return;
+    };
+    let server_position = glam::Vec3::new(me.position.x, me.position.y, me.position.z);
+    let corrected = reconcile(server_position, &prediction.pending_inputs);
+    let error = corrected - last_sent;
+    if error.length() > RECONCILE_THRESHOLD {
+        tracing::debug!(?error, "Reconciling local player");
+        let _ = corrections_tx.send(error);
+        prediction.last_sent_position = Some(corrected);
     }
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    println!("Space Engineers Clone - 3D Construction Game");
-    println!("Controls: WASD=move, Shift=sprint, Space/Ctrl=up/down, Mouse=look, F=place block, G=spawn ship, ESC=toggle mouse, F11=fullscreen");
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
+        )
+        .init();
+
+    tracing::info!("Space Engineers Clone - 3D Construction Game");
+    tracing::info!("Controls: WASD=move, Shift=sprint, Z=zoom, Space/Ctrl=up/down, Mouse=look, F=place block, G=spawn ship, +/-=render distance, F1=stats overlay, F3=frame graph, F4=fullbright, [/]=brightness, V=vsync, ESC=toggle mouse, F11=fullscreen");
 
     let event_loop = EventLoop::new()?;
     let mut app = GameApp::new();
 
     event_loop.run_app(&mut app)?;
 
+    if let Some(message) = app.startup_error {
+        eprintln!("{message}");
+        std::process::exit(1);
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game_protocol::{PlayerState, Position};
+
+    /// Snapshot whose players all sit at x = `tick`, so a torn view is detectable
+    fn snapshot(tick: u32) -> WorldSnapshot {
+        let mut snapshot = WorldSnapshot::default();
+        for id in 1..=8 {
+            snapshot.players.insert(
+                id,
+                PlayerState {
+                    name: format!("player {}", id),
+                    position: Position::new(tick as f32, id as f32, 0.0),
+                    health: 100.0,
+                    last_input_seq: tick,
+                },
+            );
+        }
+        snapshot
+    }
+
+    /// Local player as the server reports it
+    fn me(x: f32, last_input_seq: u32) -> PlayerState {
+        PlayerState {
+            name: "me".into(),
+            position: Position::new(x, 0.0, 0.0),
+            health: 100.0,
+            last_input_seq,
+        }
+    }
+
+    #[test]
+    fn reconcile_drops_acked_inputs_and_replays_pending_ones() {
+        // Four 1-unit steps along x, predicted locally up to x = 4
+        let mut prediction = Prediction {
+            input_seq: 4,
+            pending_inputs: (1..=4).map(|seq| (seq, glam::Vec3::X)).collect(),
+            last_sent_position: Some(glam::Vec3::new(4.0, 0.0, 0.0)),
+        };
+        let (corrections_tx, corrections_rx) = mpsc::channel();
+
+        // Server processed 1 and 2 and agrees: 3 and 4 replay onto x = 2
+        reconcile_local_player(&mut prediction, &corrections_tx, &me(2.0, 2));
+        let pending: Vec<u32> = prediction
+            .pending_inputs
+            .iter()
+            .map(|(seq, _)| *seq)
+            .collect();
+        assert_eq!(pending, vec![3, 4]);
+        assert!(corrections_rx.try_recv().is_err());
+
+        // 0.4 off after replaying 4: under the threshold, left alone
+        reconcile_local_player(&mut prediction, &corrections_tx, &me(3.4, 3));
+        assert_eq!(prediction.pending_inputs.len(), 1);
+        assert!(corrections_rx.try_recv().is_err());
+        assert_eq!(
+            prediction.last_sent_position,
+            Some(glam::Vec3::new(4.0, 0.0, 0.0))
+        );
+
+        // 0.6 off: the camera is moved by the error and prediction restarts there
+        reconcile_local_player(&mut prediction, &corrections_tx, &me(3.6, 3));
+        let correction = corrections_rx.try_recv().unwrap();
+        assert!((correction - glam::Vec3::new(0.6, 0.0, 0.0)).length() < 1e-5);
+        let corrected = prediction.last_sent_position.unwrap();
+        assert!((corrected - glam::Vec3::new(4.6, 0.0, 0.0)).length() < 1e-5);
+    }
+
+    #[test]
+    fn reconcile_waits_for_the_first_ack() {
+        let mut prediction = Prediction {
+            input_seq: 1,
+            pending_inputs: VecDeque::from([(1, glam::Vec3::X)]),
+            last_sent_position: Some(glam::Vec3::X),
+        };
+        let (corrections_tx, corrections_rx) = mpsc::channel();
+        reconcile_local_player(&mut prediction, &corrections_tx, &me(50.0, 0));
+        assert_eq!(prediction.pending_inputs.len(), 1);
+        assert!(corrections_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn ship_blocks_follow_the_ship_orientation() {
+        let block = |id, x| game_protocol::BlockState {
+            id,
+            name: "Armor".into(),
+            block_type: "armor".into(),
+            position: game_protocol::RelPosition::new(x, 0, 0),
+            orientation: game_protocol::RelOrientation::default(),
+            integrity: 100.0,
+            faction_id: None,
+        };
+        let ship = ShipState {
+            name: "ship".into(),
+            position: Position::new(10.0, 0.0, 0.0),
+            orientation: game_protocol::Orientation::new(0.0, std::f32::consts::FRAC_PI_2, 0.0),
+            blocks: vec![block(1, 0), block(2, 1)],
+            version: 1,
+            is_large: true,
+        };
+
+        // Yawed 90°: the ship's local +X points to world -Z, one 2.5 m cell out
+        let instances: Vec<BlockInstance> = ship_block_instances(&ship).collect();
+        assert!((instances[0].position() - glam::Vec3::new(10.0, 0.0, 0.0)).length() < 1e-4);
+        assert!((instances[1].position() - glam::Vec3::new(10.0, 0.0, -2.5)).length() < 1e-4);
+        // The cube itself is turned with the ship
+        let x_axis = instances[1].transform.transform_vector3(glam::Vec3::X);
+        assert!((x_axis - glam::Vec3::NEG_Z).length() < 1e-4);
+    }
+
+    #[test]
+    fn interpolate_position_blends_and_clamps() {
+        let t0 = std::time::Instant::now();
+        let at = |ms: u64| t0 + std::time::Duration::from_millis(ms);
+        let history = VecDeque::from([
+            (at(100), glam::Vec3::ZERO),
+            (at(150), glam::Vec3::new(10.0, 0.0, 0.0)),
+            (at(200), glam::Vec3::new(10.0, 4.0, 0.0)),
+        ]);
+
+        // Halfway between the first two samples
+        let mid = interpolate_position(&history, at(125)).unwrap();
+        assert!((mid - glam::Vec3::new(5.0, 0.0, 0.0)).length() < 1e-4);
+        let mid = interpolate_position(&history, at(175)).unwrap();
+        assert!((mid - glam::Vec3::new(10.0, 2.0, 0.0)).length() < 1e-4);
+
+        // Before the oldest / after the newest: held at the end samples
+        assert_eq!(interpolate_position(&history, t0), Some(glam::Vec3::ZERO));
+        assert_eq!(
+            interpolate_position(&history, at(500)),
+            Some(glam::Vec3::new(10.0, 4.0, 0.0))
+        );
+        assert_eq!(interpolate_position(&VecDeque::new(), t0), None);
+    }
+
+    #[test]
+    fn world_view_readers_never_see_torn_state() {
+        const TICKS: u32 = 2000;
+        let shared = SharedGameState::default();
+        let (corrections_tx, _corrections_rx) = mpsc::channel();
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    let mut last_tick = 0;
+                    loop {
+                        let Some(view) = shared.world_view() else {
+                            continue;
+                        };
+                        let players = &view.world_state.players;
+                        assert_eq!(players.len(), 8);
+                        let tick = players[&1].last_input_seq;
+                        for p in players.values() {
+                            assert_eq!(p.last_input_seq, tick);
+                            assert_eq!(p.position.x, tick as f32);
+                        }
+                        // Views only move forward
+                        assert!(tick >= last_tick);
+                        last_tick = tick;
+                        if tick == TICKS {
+                            break;
+                        }
+                    }
+                });
+            }
+
+            // Same path as the network task: apply the message, then publish
+            let mut view = WorldView::default();
+            let mut prediction = Prediction::default();
+            for tick in 1..=TICKS {
+                let message = Message::WorldSnapshot {
+                    snapshot: snapshot(tick),
+                };
+                if handle_network_message(&mut view, &mut prediction, &corrections_tx, message) {
+                    shared.publish(&view);
+                }
+            }
+        });
+
+        let view = shared.world_view().unwrap();
+        assert_eq!(view.world_state.players[&1].last_input_seq, TICKS);
+        assert_eq!(view.remote_history[&1].len(), REMOTE_HISTORY_LEN);
+    }
+}
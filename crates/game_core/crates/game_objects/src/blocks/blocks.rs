@@ -3,7 +3,7 @@
 use crate::entities::Entity;
 use crate::physics::{IntOrientation, IntPosition};
 use crate::utils::arenas::Arenas;
-use crate::utils::arenas::{with_current_write, Arena, HasId};
+use crate::utils::arenas::{with_current_write, HasId};
 use crate::utils::ids::{BlockDefId, EntityId, FactionId};
 use std::sync::Arc;
 
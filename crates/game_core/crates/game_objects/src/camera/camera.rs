@@ -1,4 +1,3 @@
-use crate::entities::Entity;
 use crate::physics::FloatPositionDelta;
 
 #[derive(Debug, Clone)]
@@ -12,7 +11,7 @@ pub struct Camera {
 impl Camera {
     pub fn new(field_of_view: f32, distance_of_view: f32, is_active: bool) -> Self {
         Self {
-            assigned_entity_position_delta: FloatPositionDelta::undefined(),
+            assigned_entity_position_delta: FloatPositionDelta::zero(),
             field_of_view,
             distance_of_view,
             is_active,
@@ -2,6 +2,7 @@ use crate::blocks::Block;
 use crate::celestials::Celestial;
 use crate::grids::Grid;
 use crate::humanoids::Humanoid;
+use crate::physics::PhysicalObject;
 use crate::utils::arenas::HasId;
 use crate::utils::ids::EntityId;
 
@@ -43,6 +44,24 @@ impl Entity {
         )
     }
     #[inline]
+    pub fn physical_object(&self) -> Option<&PhysicalObject> {
+        match self {
+            Entity::Humanoid(h) => h.physical_object.as_ref(),
+            Entity::Celestial(c) => Some(&c.physical_object),
+            Entity::Grid(g) => g.physical_object.as_ref(),
+            Entity::Block(_) => None,
+        }
+    }
+    #[inline]
+    pub fn physical_object_mut(&mut self) -> Option<&mut PhysicalObject> {
+        match self {
+            Entity::Humanoid(h) => h.physical_object.as_mut(),
+            Entity::Celestial(c) => Some(&mut c.physical_object),
+            Entity::Grid(g) => g.physical_object.as_mut(),
+            Entity::Block(_) => None,
+        }
+    }
+    #[inline]
     pub fn is_logical(&self) -> bool {
         matches!(
             self,
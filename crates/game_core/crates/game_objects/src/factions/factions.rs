@@ -9,8 +9,8 @@ pub struct Faction {
 impl Faction {
     pub fn new(id: FactionId, name: String) -> Self {
         Self {
-            id: id,
-            name: name,
+            id,
+            name,
         }
     }
 }
@@ -0,0 +1,121 @@
+// blueprints.rs — export / reconstruction d'une grille (sauvegarde, partage)
+
+use crate::blocks::{Block, BlockDef};
+use crate::entities::Entity;
+use crate::grids::{Grid, GridSizeClass};
+use crate::logics::LogicalObject;
+use crate::physics::{IntOrientation, IntPosition, PhysicalObject};
+use crate::utils::arenas::{with_current_read, with_current_write};
+use crate::utils::ids::{BlockDefId, EntityId, FactionId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlueprintBlock {
+    pub def_id: BlockDefId,
+    pub position: IntPosition,
+    pub orientation: IntOrientation,
+    pub faction_id: FactionId,
+}
+
+/// Plan d'une grille : uniquement les blocks, sans état physique
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Blueprint {
+    pub name: Option<String>,
+    pub size_class: Option<GridSizeClass>,
+    pub blocks: Vec<BlueprintBlock>,
+}
+
+/// Résultat d'une reconstruction : la grille créée et les définitions introuvables
+#[derive(Debug, Clone)]
+pub struct BlueprintLoad {
+    pub grid_id: EntityId,
+    pub missing_defs: Vec<BlockDefId>,
+}
+
+impl Grid {
+    pub fn to_blueprint(&self) -> Blueprint {
+        let blocks = with_current_read(|a| {
+            self.block_ids
+                .iter()
+                .filter_map(|&bid| {
+                    let h = a.get_entity(bid)?;
+                    // verrou empoisonné : block ignoré, comme une entité absente
+                    let g = h.read().ok()?;
+                    let Entity::Block(ref b) = *g else {
+                        return None;
+                    };
+                    Some(BlueprintBlock {
+                        def_id: b.def.id.clone(),
+                        position: b.position.clone(),
+                        orientation: b.orientation.clone(),
+                        faction_id: b.faction_id,
+                    })
+                })
+                .collect()
+        });
+
+        Blueprint {
+            name: self.name.clone(),
+            size_class: self.size_class.clone(),
+            blocks,
+        }
+    }
+
+    /// Crée une nouvelle grille depuis un plan. Les blocks dont la définition
+    /// est absente de `defs` sont ignorés et listés dans `missing_defs`.
+    pub fn from_blueprint(
+        blueprint: &Blueprint,
+        defs: &HashMap<BlockDefId, Arc<BlockDef>>,
+        physical_object: Option<PhysicalObject>,
+    ) -> BlueprintLoad {
+        let grid_id = Grid::spawn(
+            blueprint.name.clone(),
+            physical_object,
+            Some(LogicalObject::new(None)),
+            blueprint.size_class.clone(),
+        );
+
+        let mut missing_defs = Vec::new();
+        with_current_write(|a| {
+            let mut block_ids = Vec::with_capacity(blueprint.blocks.len());
+            for bb in &blueprint.blocks {
+                let Some(def) = defs.get(&bb.def_id) else {
+                    if !missing_defs.contains(&bb.def_id) {
+                        missing_defs.push(bb.def_id.clone());
+                    }
+                    continue;
+                };
+                let id = a.alloc_entity_id();
+                let block = Block::new(
+                    id,
+                    grid_id,
+                    def.clone(),
+                    bb.position.clone(),
+                    bb.orientation.clone(),
+                    def.integrity,
+                    bb.faction_id,
+                );
+                let _ = a.set_entity(id, Arc::new(RwLock::new(Entity::Block(block))));
+                a.tag_entity(id);
+                a.tag_block(id);
+                block_ids.push(id);
+            }
+
+            // grille absente ou verrou empoisonné : les blocks restent orphelins
+            if let Some(gh) = a.get_entity(grid_id) {
+                if let Ok(mut gw) = gh.write() {
+                    if let Entity::Grid(ref mut grid) = *gw {
+                        grid.set_block_ids(block_ids);
+                    }
+                }
+            }
+        });
+
+        BlueprintLoad {
+            grid_id,
+            missing_defs,
+        }
+    }
+}
@@ -1,20 +1,86 @@
 // grids.rs — Grid ne stocke que les EntityId de blocks ; remove() récursif
 
-use crate::blocks::Block;
+use crate::blocks::{Block, BlockDef};
 use crate::entities::Entity;
 use crate::logics::{LogicalObject, LogicalObjectDelta};
 use crate::physics::boundaries::RectBoundaries;
-use crate::physics::{PhysicalObject, PhysicalObjectDelta};
-use crate::utils::arenas::with_current_write;
+use crate::physics::{cell_size, IntOrientation, IntPosition, PhysicalObject, PhysicalObjectDelta};
+use crate::utils::arenas::{with_current_read, with_current_write};
 use crate::utils::ids::EntityId;
+use glam::{Mat4, Quat, Vec3};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::{Arc, RwLock};
 
-#[derive(Debug, Clone, Hash)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub enum GridSizeClass {
     Small,
     Large,
 }
 
+impl GridSizeClass {
+    /// Arête d'une cellule, en mètres
+    pub fn cell_size(&self) -> f32 {
+        cell_size(matches!(self, GridSizeClass::Large))
+    }
+}
+
+/// Propriétés de masse d'une grille, en repère local de la grille (mètres)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MassProperties {
+    pub total_mass: f32,
+    pub center_of_mass: Vec3,
+    /// Diagonale du tenseur d'inertie autour du centre de masse
+    pub inertia: Vec3,
+}
+
+type Cell = (i32, i32, i32);
+
+fn footprint_cells(pos: &IntPosition, (fx, fy, fz): (i32, i32, i32)) -> Vec<Cell> {
+    let mut cells = Vec::new();
+    for dx in 0..fx.max(1) {
+        for dy in 0..fy.max(1) {
+            for dz in 0..fz.max(1) {
+                cells.push((pos.x + dx, pos.y + dy, pos.z + dz));
+            }
+        }
+    }
+    cells
+}
+
+/// Transformation locale -> monde d'un block en `position`/`orientation`, sur une
+/// grille placée en `grid_position`/`grid_rotation` avec des cellules d'arête
+/// `cell`. Partagée par `Grid::block_world_transform` et le client, qui ne
+/// reçoit que l'état réseau des grilles.
+pub fn block_transform(
+    grid_position: Vec3,
+    grid_rotation: Quat,
+    cell: f32,
+    position: &IntPosition,
+    orientation: &IntOrientation,
+) -> Mat4 {
+    Mat4::from_rotation_translation(grid_rotation, grid_position)
+        * Mat4::from_rotation_translation(orientation.to_quat(), position.to_vec3() * cell)
+}
+
+/// Footprint tourné par `orient`, arrondi à la cellule (au moins 1 par axe)
+fn oriented_footprint((fx, fy, fz): (i32, i32, i32), orient: &IntOrientation) -> (i32, i32, i32) {
+    let extent = orient.to_quat() * Vec3::new(fx.max(1) as f32, fy.max(1) as f32, fz.max(1) as f32);
+    let extent = extent.abs().round().max(Vec3::ONE);
+    (extent.x as i32, extent.y as i32, extent.z as i32)
+}
+
+fn neighbors((x, y, z): Cell) -> [Cell; 6] {
+    [
+        (x + 1, y, z),
+        (x - 1, y, z),
+        (x, y + 1, z),
+        (x, y - 1, z),
+        (x, y, z + 1),
+        (x, y, z - 1),
+    ]
+}
+
 #[derive(Debug, Clone)]
 pub struct Grid {
     pub id: EntityId,
@@ -36,7 +102,7 @@ impl Grid {
         size_class: Option<GridSizeClass>,
     ) -> EntityId {
         with_current_write(|a| {
-            let id = a.alloc_entity_id();
+            let id = a.alloc_physical_entity_id();
 
             let g = Grid {
                 id,
@@ -50,11 +116,8 @@ impl Grid {
             };
 
             let e = Arc::new(RwLock::new(Entity::Grid(g)));
-            let back = a.insert_entity(e);
-            debug_assert_eq!(back, id);
+            let _ = a.set_entity(id, e);
 
-            a.tag_entity(id);
-            a.tag_physical(id);
             a.tag_logical(id);
             id
         })
@@ -142,6 +205,215 @@ impl Grid {
         self.block_ids = ids;
     }
 
+    /// Arête de cellule de cette grille ; large par défaut sans classe de taille
+    pub fn cell_size(&self) -> f32 {
+        self.size_class
+            .as_ref()
+            .map_or(cell_size(true), GridSizeClass::cell_size)
+    }
+
+    // ---------- Masse ----------
+    /// Masse totale, centre de masse et inertie, calculés à la demande depuis
+    /// `current_mass` et la position des blocks (toujours à jour après un delta).
+    pub fn mass_properties(&self) -> MassProperties {
+        let cell = self.cell_size();
+
+        // (masse, centre, demi-taille) de chaque block
+        let blocks: Vec<(f32, Vec3, Vec3)> = with_current_read(|a| {
+            self.block_ids
+                .iter()
+                .filter_map(|&bid| {
+                    let h = a.get_entity(bid)?;
+                    let g = h.read().unwrap();
+                    let Entity::Block(ref b) = *g else {
+                        return None;
+                    };
+                    let (fx, fy, fz) = oriented_footprint(b.def.footprint, &b.orientation);
+                    let fp = Vec3::new(fx as f32, fy as f32, fz as f32);
+                    let center = (b.position.to_vec3() + (fp - Vec3::ONE) * 0.5) * cell;
+                    Some((b.current_mass, center, fp * cell * 0.5))
+                })
+                .collect()
+        });
+
+        let total_mass: f32 = blocks.iter().map(|(m, _, _)| m).sum();
+        if total_mass <= 0.0 {
+            return MassProperties {
+                total_mass: 0.0,
+                center_of_mass: Vec3::ZERO,
+                inertia: Vec3::ZERO,
+            };
+        }
+        let center_of_mass = blocks.iter().map(|(m, c, _)| *c * *m).sum::<Vec3>() / total_mass;
+
+        // boîte pleine + Huygens-Steiner par axe
+        let inertia = blocks.iter().fold(Vec3::ZERO, |acc, (m, c, h)| {
+            let d = *c - center_of_mass;
+            let s = *h * 2.0;
+            let box_i = Vec3::new(
+                s.y * s.y + s.z * s.z,
+                s.x * s.x + s.z * s.z,
+                s.x * s.x + s.y * s.y,
+            ) * (*m / 12.0);
+            let offset = Vec3::new(
+                d.y * d.y + d.z * d.z,
+                d.x * d.x + d.z * d.z,
+                d.x * d.x + d.y * d.y,
+            ) * *m;
+            acc + box_i + offset
+        });
+
+        MassProperties {
+            total_mass,
+            center_of_mass,
+            inertia,
+        }
+    }
+
+    // ---------- Intégrité structurelle ----------
+    /// Applique `delta` à l'intégrité d'un block (bornée à [0, def.integrity]).
+    /// À zéro, le block est détruit et la grille re-analysée : retourne les
+    /// groupes détachés (tous sauf le plus grand), à faire apparaître comme
+    /// nouvelles grilles. Vide si la grille reste d'un seul tenant.
+    pub fn apply_integrity_delta(block_id: EntityId, delta: f32) -> Vec<Vec<EntityId>> {
+        let destroyed_in = with_current_write(|a| {
+            let h = a.get_entity(block_id)?;
+            let grid_id = {
+                let mut g = h.write().unwrap();
+                let Entity::Block(ref mut b) = *g else {
+                    return None;
+                };
+                b.current_integrity = (b.current_integrity + delta).clamp(0.0, b.def.integrity);
+                if b.current_integrity > 0.0 {
+                    return None;
+                }
+                b.grid_id
+            };
+            Block::remove_with_ctx(a, block_id);
+            Some(grid_id)
+        });
+        let Some(grid_id) = destroyed_in else {
+            return Vec::new();
+        };
+
+        // Copie de la grille : connected_groups reprend le verrou de l'arène
+        let Some(h) = with_current_read(|a| a.get_entity(grid_id)) else {
+            return Vec::new();
+        };
+        let grid = match *h.read().unwrap() {
+            Entity::Grid(ref grid) => grid.clone(),
+            _ => return Vec::new(),
+        };
+        let mut groups = grid.connected_groups();
+        // Le plus grand groupe (le premier à égalité) reste la grille d'origine
+        if let Some(main) = (0..groups.len()).max_by_key(|&i| (groups[i].len(), usize::MAX - i)) {
+            groups.remove(main);
+        }
+        groups
+    }
+
+    /// Groupes de blocks connexes (adjacence 6 entre cellules occupées, footprint inclus).
+    /// Plus d'un groupe = la grille s'est scindée.
+    pub fn connected_groups(&self) -> Vec<Vec<EntityId>> {
+        let blocks = self.block_cells();
+
+        let mut occupied: HashMap<Cell, usize> = HashMap::new();
+        for (i, (_, cells)) in blocks.iter().enumerate() {
+            for &c in cells {
+                occupied.insert(c, i);
+            }
+        }
+
+        // BFS sur les blocks
+        let mut visited = vec![false; blocks.len()];
+        let mut groups = Vec::new();
+        for start in 0..blocks.len() {
+            if visited[start] {
+                continue;
+            }
+            visited[start] = true;
+            let mut queue = VecDeque::from([start]);
+            let mut group = Vec::new();
+            while let Some(i) = queue.pop_front() {
+                group.push(blocks[i].0);
+                for &c in &blocks[i].1 {
+                    for n in neighbors(c) {
+                        if let Some(&j) = occupied.get(&n) {
+                            if !visited[j] {
+                                visited[j] = true;
+                                queue.push_back(j);
+                            }
+                        }
+                    }
+                }
+            }
+            groups.push(group);
+        }
+        groups
+    }
+
+    /// Cellules occupées par chaque block (footprint orienté inclus)
+    fn block_cells(&self) -> Vec<(EntityId, Vec<Cell>)> {
+        with_current_read(|a| {
+            self.block_ids
+                .iter()
+                .filter_map(|&bid| {
+                    let h = a.get_entity(bid)?;
+                    let g = h.read().unwrap();
+                    let Entity::Block(ref b) = *g else {
+                        return None;
+                    };
+                    let footprint = oriented_footprint(b.def.footprint, &b.orientation);
+                    Some((bid, footprint_cells(&b.position, footprint)))
+                })
+                .collect()
+        })
+    }
+
+    // ---------- Repères ----------
+    /// Transformation locale -> monde d'un block : position/orientation de la
+    /// grille composées avec la cellule et l'orientation du block.
+    pub fn block_world_transform(&self, block: &Block) -> Mat4 {
+        let cell = self.cell_size();
+        let po = self.physical_object.as_ref();
+        let grid_position = po
+            .and_then(|po| po.position.as_ref())
+            .map_or(Vec3::ZERO, |p| p.to_vec3());
+        let grid_rotation = po
+            .and_then(|po| po.orientation.as_ref())
+            .map_or(Quat::IDENTITY, |o| o.to_quat());
+        block_transform(
+            grid_position,
+            grid_rotation,
+            cell,
+            &block.position,
+            &block.orientation,
+        )
+    }
+
+    // ---------- Placement ----------
+    /// Vrai si `def` peut être posé en `pos` : aucune cellule du footprint déjà
+    /// occupée, et au moins une cellule voisine occupée (sauf premier block).
+    /// Pas de mount points dans `BlockDef` : toute face adjacente compte.
+    pub fn can_place(&self, def: &BlockDef, pos: &IntPosition, orient: &IntOrientation) -> bool {
+        let cells = footprint_cells(pos, oriented_footprint(def.footprint, orient));
+
+        let occupied: HashSet<Cell> = self
+            .block_cells()
+            .into_iter()
+            .flat_map(|(_, c)| c)
+            .collect();
+        if occupied.is_empty() {
+            return true;
+        }
+        if cells.iter().any(|c| occupied.contains(c)) {
+            return false;
+        }
+        cells
+            .iter()
+            .any(|&c| neighbors(c).iter().any(|n| occupied.contains(n)))
+    }
+
     // ---------- Deltas ----------
     pub fn record_delta(&mut self, delta: GridDelta) {
         self.pending_deltas.push(delta);
@@ -176,12 +448,19 @@ impl GridDelta {
         deltas.sort_by_key(|d| d.timestamp);
         let mut m = deltas.remove(0);
         for d in deltas {
-            if d.physical_object_delta.is_some() {
-                m.physical_object_delta = d.physical_object_delta;
-            }
-            if d.logical_object_delta.is_some() {
-                m.logical_object_delta = d.logical_object_delta;
+            if d.timestamp.is_some() {
+                m.timestamp = d.timestamp;
             }
+            // sous-deltas fusionnés champ par champ (pas de remplacement en bloc)
+            m.physical_object_delta =
+                match (m.physical_object_delta.take(), d.physical_object_delta) {
+                    (Some(a), Some(b)) => PhysicalObjectDelta::merge(vec![a, b]),
+                    (a, b) => b.or(a),
+                };
+            m.logical_object_delta = match (m.logical_object_delta.take(), d.logical_object_delta) {
+                (Some(a), Some(b)) => LogicalObjectDelta::merge(vec![a, b]),
+                (a, b) => b.or(a),
+            };
             if d.block_ids.is_some() {
                 m.block_ids = d.block_ids;
             }
@@ -208,3 +487,450 @@ impl GridDelta {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grids::blueprints::{Blueprint, BlueprintBlock};
+    use crate::physics::{FloatOrientation, FloatPosition, Velocity};
+    use crate::utils::ids::{BlockDefId, FactionId};
+    use crate::world::World;
+
+    /// Lacet de 2 rad : arrondi cellule par cellule, le footprint (2, 1, 1)
+    /// devient (1, 1, 2)
+    const YAW_TURN: i32 = 2;
+
+    fn cube() -> Arc<BlockDef> {
+        Arc::new(BlockDef::new(
+            BlockDefId::Large(1),
+            "Cube",
+            (1, 1, 1),
+            100.0,
+            100.0,
+            "armor",
+        ))
+    }
+
+    fn beam() -> Arc<BlockDef> {
+        Arc::new(BlockDef::new(
+            BlockDefId::Large(2),
+            "Beam",
+            (2, 1, 1),
+            200.0,
+            100.0,
+            "armor",
+        ))
+    }
+
+    /// (def, cellule, lacet) d'un block à poser
+    type Placed = (Arc<BlockDef>, Cell, i32);
+
+    /// Grille construite depuis des blocks posés ; le monde doit rester en scope
+    /// pendant toute la durée du test
+    fn grid_with(blocks: &[Placed]) -> Grid {
+        let defs: HashMap<BlockDefId, Arc<BlockDef>> = blocks
+            .iter()
+            .map(|(d, _, _)| (d.id.clone(), d.clone()))
+            .collect();
+        let blueprint = Blueprint {
+            name: None,
+            size_class: Some(GridSizeClass::Large),
+            blocks: blocks
+                .iter()
+                .map(|(d, (x, y, z), yaw)| BlueprintBlock {
+                    def_id: d.id.clone(),
+                    position: IntPosition::new(*x, *y, *z),
+                    orientation: IntOrientation::new(0, *yaw, 0),
+                    faction_id: FactionId(0),
+                })
+                .collect(),
+        };
+        let load = Grid::from_blueprint(&blueprint, &defs, None);
+        grid(load.grid_id)
+    }
+
+    /// Copie de la grille `id` du monde courant
+    fn grid(id: EntityId) -> Grid {
+        with_current_read(|a| {
+            let h = a.get_entity(id).unwrap();
+            let g = h.read().unwrap();
+            match *g {
+                Entity::Grid(ref grid) => grid.clone(),
+                _ => unreachable!(),
+            }
+        })
+    }
+
+    fn defs_of(defs: &[Arc<BlockDef>]) -> HashMap<BlockDefId, Arc<BlockDef>> {
+        defs.iter().map(|d| (d.id.clone(), d.clone())).collect()
+    }
+
+    #[test]
+    fn blueprint_round_trip() {
+        let world = World::new(0, "test".into());
+        let _scope = world.scope();
+        let original = grid_with(&[
+            (cube(), (0, 0, 0), 0),
+            (beam(), (0, 0, 1), YAW_TURN),
+            (cube(), (0, 0, 3), 0),
+        ]);
+        let blueprint = original.to_blueprint();
+        assert_eq!(blueprint.blocks.len(), 3);
+
+        // Sauvegardé puis relu, le plan reconstruit une grille identique
+        let saved: Blueprint =
+            bincode::deserialize(&bincode::serialize(&blueprint).unwrap()).unwrap();
+        assert_eq!(saved, blueprint);
+        let load = Grid::from_blueprint(&saved, &defs_of(&[cube(), beam()]), None);
+        assert!(load.missing_defs.is_empty());
+        assert_ne!(load.grid_id, original.id);
+        let rebuilt = grid(load.grid_id);
+        assert_eq!(rebuilt.to_blueprint(), blueprint);
+        assert_eq!(rebuilt.connected_groups().len(), 1);
+    }
+
+    #[test]
+    fn blueprint_skips_and_reports_missing_defs() {
+        let world = World::new(0, "test".into());
+        let _scope = world.scope();
+        let blueprint = grid_with(&[
+            (cube(), (0, 0, 0), 0),
+            (beam(), (1, 0, 0), 0),
+            (beam(), (1, 1, 0), 0),
+        ])
+        .to_blueprint();
+
+        // Sans la poutre : deux blocks ignorés, sa définition signalée une fois
+        let load = Grid::from_blueprint(&blueprint, &defs_of(&[cube()]), None);
+        assert_eq!(load.missing_defs, vec![beam().id.clone()]);
+        let partial = grid(load.grid_id).to_blueprint();
+        assert_eq!(partial.blocks, blueprint.blocks[..1]);
+    }
+
+    #[test]
+    fn blueprint_skips_poisoned_blocks() {
+        let world = World::new(0, "test".into());
+        let _scope = world.scope();
+        let grid = grid_with(&[(cube(), (0, 0, 0), 0), (cube(), (1, 0, 0), 0)]);
+        let poisoned = with_current_read(|a| a.get_entity(grid.block_ids[0]).unwrap());
+        let _ = std::thread::spawn(move || {
+            let _g = poisoned.write().unwrap();
+            panic!("poisons the block lock");
+        })
+        .join();
+
+        let blueprint = grid.to_blueprint();
+        assert_eq!(blueprint.blocks.len(), 1);
+        assert_eq!(blueprint.blocks[0].position, IntPosition::new(1, 0, 0));
+    }
+
+    fn physical_delta(
+        position: Option<FloatPosition>,
+        velocity: Option<Velocity>,
+    ) -> PhysicalObjectDelta {
+        PhysicalObjectDelta {
+            timestamp: None,
+            position,
+            orientation: None,
+            velocity,
+            acceleration: None,
+            mass: None,
+            boundaries: None,
+            map_id: None,
+        }
+    }
+
+    #[test]
+    fn merge_keeps_last_writer_per_field() {
+        let older = GridDelta {
+            timestamp: Some(1),
+            physical_object_delta: Some(physical_delta(
+                Some(FloatPosition::new(1.0, 0.0, 0.0)),
+                Some(Velocity::new(3.0, 0.0, 0.0)),
+            )),
+            logical_object_delta: Some(LogicalObjectDelta {
+                timestamp: Some(1),
+                ops: Vec::new(),
+            }),
+            block_ids: Some(vec![EntityId(1), EntityId(2)]),
+        };
+        let newer = GridDelta {
+            timestamp: Some(2),
+            physical_object_delta: Some(physical_delta(
+                Some(FloatPosition::new(2.0, 0.0, 0.0)),
+                None,
+            )),
+            logical_object_delta: None,
+            block_ids: Some(vec![EntityId(3)]),
+        };
+
+        // Trié par timestamp : l'ordre du Vec n'importe pas
+        let m = GridDelta::merge(vec![newer, older.clone()]).unwrap();
+        assert_eq!(m.timestamp, Some(2));
+        assert_eq!(m.block_ids, Some(vec![EntityId(3)]));
+        let pod = m.physical_object_delta.unwrap();
+        assert_eq!(pod.position.unwrap().x, 2.0);
+        // Champ absent du plus récent : la valeur précédente reste
+        assert_eq!(pod.velocity.unwrap().x, 3.0);
+        assert!(m.logical_object_delta.is_some());
+
+        // Un delta sans sous-delta ni ids n'efface rien
+        let empty = GridDelta {
+            timestamp: Some(3),
+            physical_object_delta: None,
+            logical_object_delta: None,
+            block_ids: None,
+        };
+        let m = GridDelta::merge(vec![older, empty]).unwrap();
+        assert_eq!(m.timestamp, Some(3));
+        assert_eq!(m.block_ids, Some(vec![EntityId(1), EntityId(2)]));
+        assert_eq!(m.physical_object_delta.unwrap().position.unwrap().x, 1.0);
+        assert!(m.logical_object_delta.is_some());
+    }
+
+    #[test]
+    fn merge_of_nothing_is_none() {
+        assert!(GridDelta::merge(Vec::new()).is_none());
+    }
+
+    #[test]
+    fn block_world_transform_follows_grid_yaw() {
+        let world = World::new(0, "test".into());
+        let _scope = world.scope();
+        let mut grid = grid_with(&[(cube(), (0, 0, 0), 0), (cube(), (1, 0, 0), 0)]);
+        grid.physical_object = Some(PhysicalObject::new(
+            None,
+            Some(FloatPosition::new(10.0, 0.0, 0.0)),
+            Some(FloatOrientation::new(0.0, std::f32::consts::FRAC_PI_2, 0.0)),
+            None,
+            None,
+            None,
+            None,
+            None,
+            Vec::new(),
+        ));
+        let block = with_current_read(|a| {
+            let h = a.get_entity(grid.block_ids[1]).unwrap();
+            let g = h.read().unwrap();
+            match *g {
+                Entity::Block(ref b) => b.clone(),
+                _ => unreachable!(),
+            }
+        });
+
+        // Lacet de +90° : le +X local de la grille pointe vers -Z, à une cellule (2.5 m)
+        let transform = grid.block_world_transform(&block);
+        let expected = Vec3::new(10.0, 0.0, -2.5);
+        assert!((transform.transform_point3(Vec3::ZERO) - expected).length() < 1e-4);
+        // L'axe +X du block suit la grille
+        assert!((transform.transform_vector3(Vec3::X) - Vec3::NEG_Z).length() < 1e-4);
+    }
+
+    #[test]
+    fn oriented_footprint_swaps_axes() {
+        assert_eq!(
+            oriented_footprint((2, 1, 1), &IntOrientation::identity()),
+            (2, 1, 1)
+        );
+        assert_eq!(
+            oriented_footprint((2, 1, 1), &IntOrientation::new(0, YAW_TURN, 0)),
+            (1, 1, 2)
+        );
+    }
+
+    #[test]
+    fn can_place_first_block_anywhere() {
+        let world = World::new(0, "test".into());
+        let _scope = world.scope();
+        let grid = grid_with(&[]);
+        assert!(grid.can_place(
+            &cube(),
+            &IntPosition::new(7, -3, 2),
+            &IntOrientation::identity()
+        ));
+    }
+
+    #[test]
+    fn can_place_adjacent_block() {
+        let world = World::new(0, "test".into());
+        let _scope = world.scope();
+        let grid = grid_with(&[(cube(), (0, 0, 0), 0)]);
+        assert!(grid.can_place(
+            &cube(),
+            &IntPosition::new(1, 0, 0),
+            &IntOrientation::identity()
+        ));
+        assert!(grid.can_place(
+            &beam(),
+            &IntPosition::new(0, 1, 0),
+            &IntOrientation::identity()
+        ));
+    }
+
+    #[test]
+    fn can_place_rejects_floating_block() {
+        let world = World::new(0, "test".into());
+        let _scope = world.scope();
+        let grid = grid_with(&[(cube(), (0, 0, 0), 0)]);
+        assert!(!grid.can_place(
+            &cube(),
+            &IntPosition::new(2, 0, 0),
+            &IntOrientation::identity()
+        ));
+        // Contact en diagonale seulement
+        assert!(!grid.can_place(
+            &cube(),
+            &IntPosition::new(1, 1, 0),
+            &IntOrientation::identity()
+        ));
+    }
+
+    #[test]
+    fn can_place_rejects_overlap() {
+        let world = World::new(0, "test".into());
+        let _scope = world.scope();
+        let grid = grid_with(&[(beam(), (0, 0, 0), 0)]);
+        assert!(!grid.can_place(
+            &cube(),
+            &IntPosition::new(0, 0, 0),
+            &IntOrientation::identity()
+        ));
+        // Deuxième cellule de la poutre
+        assert!(!grid.can_place(
+            &cube(),
+            &IntPosition::new(1, 0, 0),
+            &IntOrientation::identity()
+        ));
+        // Nouveau block orienté dont le footprint recouvre la poutre
+        assert!(!grid.can_place(
+            &beam(),
+            &IntPosition::new(1, 0, -1),
+            &IntOrientation::new(0, YAW_TURN, 0)
+        ));
+    }
+
+    #[test]
+    fn can_place_uses_orientation_of_existing_blocks() {
+        let world = World::new(0, "test".into());
+        let _scope = world.scope();
+        // Poutre tournée : occupe (0,0,0) et (0,0,1), pas (1,0,0)
+        let grid = grid_with(&[(beam(), (0, 0, 0), YAW_TURN)]);
+        assert!(!grid.can_place(
+            &cube(),
+            &IntPosition::new(0, 0, 1),
+            &IntOrientation::identity()
+        ));
+        assert!(grid.can_place(
+            &cube(),
+            &IntPosition::new(1, 0, 0),
+            &IntOrientation::identity()
+        ));
+        assert!(grid.can_place(
+            &cube(),
+            &IntPosition::new(0, 0, 2),
+            &IntOrientation::identity()
+        ));
+    }
+
+    #[test]
+    fn connected_groups_detects_split() {
+        let world = World::new(0, "test".into());
+        let _scope = world.scope();
+        let grid = grid_with(&[
+            (cube(), (0, 0, 0), 0),
+            (cube(), (1, 0, 0), 0),
+            (cube(), (5, 0, 0), 0),
+        ]);
+        let mut sizes: Vec<usize> = grid.connected_groups().iter().map(Vec::len).collect();
+        sizes.sort_unstable();
+        assert_eq!(sizes, vec![1, 2]);
+    }
+
+    #[test]
+    fn destroying_a_block_returns_detached_groups() {
+        let world = World::new(0, "test".into());
+        let _scope = world.scope();
+        let line: Vec<Placed> = (0..5).map(|x| (cube(), (x, 0, 0), 0)).collect();
+        let grid = grid_with(&line);
+        let ids = grid.block_ids.clone();
+
+        // Dégâts partiels : rien ne se détache
+        assert!(Grid::apply_integrity_delta(ids[2], -40.0).is_empty());
+        assert!(with_current_read(|a| a.get_entity(ids[2]).is_some()));
+
+        // Le block du milieu tombe à zéro : deux moitiés égales, la première
+        // reste la grille d'origine et la seconde est retournée
+        assert_eq!(
+            Grid::apply_integrity_delta(ids[2], -1000.0),
+            vec![vec![ids[3], ids[4]]]
+        );
+        assert!(with_current_read(|a| a.get_entity(ids[2]).is_none()));
+        // Sans respawn, la moitié détachée est encore dans la grille : le plus
+        // grand groupe reste, même s'il ne contient pas le premier block
+        assert_eq!(
+            Grid::apply_integrity_delta(ids[0], -100.0),
+            vec![vec![ids[1]]]
+        );
+    }
+
+    #[test]
+    fn connected_groups_follows_oriented_footprint() {
+        let world = World::new(0, "test".into());
+        let _scope = world.scope();
+        // Tournée, la poutre relie (0,0,0) à (0,0,3) ; sans rotation elle partirait en x
+        let bridged = grid_with(&[
+            (cube(), (0, 0, 0), 0),
+            (beam(), (0, 0, 1), YAW_TURN),
+            (cube(), (0, 0, 3), 0),
+        ]);
+        assert_eq!(bridged.connected_groups().len(), 1);
+        let split = grid_with(&[
+            (cube(), (0, 0, 0), 0),
+            (beam(), (0, 0, 1), 0),
+            (cube(), (0, 0, 3), 0),
+        ]);
+        assert_eq!(split.connected_groups().len(), 2);
+    }
+
+    #[test]
+    fn mass_properties_of_empty_grid() {
+        let world = World::new(0, "test".into());
+        let _scope = world.scope();
+        let mp = grid_with(&[]).mass_properties();
+        assert_eq!(mp.total_mass, 0.0);
+        assert_eq!(mp.inertia, Vec3::ZERO);
+    }
+
+    #[test]
+    fn mass_properties_of_two_cubes() {
+        let world = World::new(0, "test".into());
+        let _scope = world.scope();
+        let grid = grid_with(&[(cube(), (0, 0, 0), 0), (cube(), (2, 0, 0), 0)]);
+        let cell = grid.cell_size();
+        let mp = grid.mass_properties();
+        assert_eq!(mp.total_mass, 200.0);
+        assert!((mp.center_of_mass - Vec3::new(cell, 0.0, 0.0)).length() < 1e-4);
+        // Deux cubes pleins + Huygens-Steiner le long de x
+        let cube_i = 100.0 * 2.0 * cell * cell / 12.0;
+        let expected = Vec3::new(
+            2.0 * cube_i,
+            2.0 * cube_i + 200.0 * cell * cell,
+            2.0 * cube_i + 200.0 * cell * cell,
+        );
+        assert!((mp.inertia - expected).length() < 1e-2);
+    }
+
+    #[test]
+    fn mass_properties_follows_oriented_footprint() {
+        let world = World::new(0, "test".into());
+        let _scope = world.scope();
+        let grid = grid_with(&[(beam(), (0, 0, 0), YAW_TURN)]);
+        let cell = grid.cell_size();
+        let mp = grid.mass_properties();
+        // Le centre de la poutre tournée est entre (0,0,0) et (0,0,1)
+        assert!((mp.center_of_mass - Vec3::new(0.0, 0.0, 0.5 * cell)).length() < 1e-4);
+        // Allongée en z : inertie minimale autour de z
+        assert!(mp.inertia.z < mp.inertia.x);
+        assert!((mp.inertia.x - mp.inertia.y).abs() < 1e-2);
+    }
+}
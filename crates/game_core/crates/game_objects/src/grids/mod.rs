@@ -1,3 +1,5 @@
+pub mod blueprints;
 pub mod grids;
 
+pub use blueprints::*;
 pub use grids::*;
@@ -20,7 +20,7 @@ pub struct Humanoid {
 impl Humanoid {
     pub fn spawn(name: Option<String>, physical_object: Option<PhysicalObject>) -> EntityId {
         with_current_write(|a| {
-            let id = a.alloc_entity_id();
+            let id = a.alloc_physical_entity_id();
 
             // LogicalObject + composants locaux
             let mut lo = LogicalObject::new(None);
@@ -35,11 +35,8 @@ impl Humanoid {
             };
 
             let e = Arc::new(RwLock::new(Entity::Humanoid(h)));
-            let back = a.insert_entity(e);
-            debug_assert_eq!(back, id);
+            let _ = a.set_entity(id, e);
 
-            a.tag_entity(id);
-            a.tag_physical(id);
             a.tag_logical(id);
             a.tag_humanoid(id);
             id
@@ -107,12 +104,19 @@ impl HumanoidDelta {
         deltas.sort_by_key(|d| d.timestamp);
         let mut m = deltas.remove(0);
         for d in deltas {
-            if d.physical_object_delta.is_some() {
-                m.physical_object_delta = d.physical_object_delta;
-            }
-            if d.logical_object_delta.is_some() {
-                m.logical_object_delta = d.logical_object_delta;
+            if d.timestamp.is_some() {
+                m.timestamp = d.timestamp;
             }
+            // sous-deltas fusionnés champ par champ (pas de remplacement en bloc)
+            m.physical_object_delta =
+                match (m.physical_object_delta.take(), d.physical_object_delta) {
+                    (Some(a), Some(b)) => PhysicalObjectDelta::merge(vec![a, b]),
+                    (a, b) => b.or(a),
+                };
+            m.logical_object_delta = match (m.logical_object_delta.take(), d.logical_object_delta) {
+                (Some(a), Some(b)) => LogicalObjectDelta::merge(vec![a, b]),
+                (a, b) => b.or(a),
+            };
         }
         Some(m)
     }
@@ -1,10 +1,27 @@
-use crate::items::{Item, ItemStack};
+use crate::items::{Item, ItemId, ItemStack};
+
+/// Returned when an inventory cannot hold the requested quantity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InventoryFull {
+    pub requested: u32,
+    pub available: u32,
+}
+
+/// Change to an inventory's contents, recorded by transfers so both sides
+/// can be replicated (see `Inventory::take_changes`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ComponentChange {
+    /// `delta` units of `item_id` added (positive) or removed (negative)
+    Inventory { item_id: ItemId, delta: i64 },
+}
 
 #[derive(Debug, Clone)]
 pub struct Inventory {
     slots: Vec<ItemStack>,
     max_volume: f32, // in cubic meters
     max_mass: f32,   // in kg
+    /// Changes made by transfers since the last `take_changes`
+    pending_changes: Vec<ComponentChange>,
 }
 
 impl Inventory {
@@ -13,7 +30,27 @@ impl Inventory {
             slots: Vec::new(),
             max_volume,
             max_mass,
+            pending_changes: Vec::new(),
+        }
+    }
+
+    /// Drains the changes recorded since the last call, oldest first.
+    pub fn take_changes(&mut self) -> Vec<ComponentChange> {
+        std::mem::take(&mut self.pending_changes)
+    }
+
+    /// Records `moved` units leaving `from` and entering `to`.
+    fn record_transfer(from: &mut Inventory, to: &mut Inventory, item_id: &ItemId, moved: u32) {
+        if moved == 0 {
+            return;
         }
+        let (item_id, delta) = (*item_id, i64::from(moved));
+        from.pending_changes.push(ComponentChange::Inventory {
+            item_id,
+            delta: -delta,
+        });
+        to.pending_changes
+            .push(ComponentChange::Inventory { item_id, delta });
     }
 
     pub fn current_volume(&self) -> f32 {
@@ -25,33 +62,132 @@ impl Inventory {
     }
 
     pub fn max_mass_to_add(&self, item: &Item, quantity: u32) -> u32 {
-        (((self.max_mass - self.current_mass()) / &item.mass_per_unit).floor() as u32).min(quantity)
+        (((self.max_mass - self.current_mass()) / item.mass_per_unit).floor() as u32).min(quantity)
     }
 
     pub fn max_volume_to_add(&self, item: &Item, quantity: u32) -> u32 {
-        (((self.max_volume - self.current_volume()) / &item.volume_per_unit).floor() as u32)
+        (((self.max_volume - self.current_volume()) / item.volume_per_unit).floor() as u32)
             .min(quantity)
     }
 
     pub fn max_to_add(&self, item: &Item, quantity: u32) -> u32 {
-        self.max_mass_to_add(&item, quantity)
-            .min(self.max_volume_to_add(&item, quantity))
+        self.max_mass_to_add(item, quantity)
+            .min(self.max_volume_to_add(item, quantity))
     }
 
     pub fn can_add(&self, item: &Item, quantity: u32) -> bool {
-        self.max_to_add(&item, quantity) > 0
+        self.max_to_add(item, quantity) > 0
     }
 
     pub fn add(&mut self, item: Item, quantity: u32) -> bool {
         if self.can_add(&item, quantity) {
-            if let Some(stack) = self.slots.iter_mut().find(|s| s.item.id == item.id) {
-                stack.add(quantity);
+            self.insert(item, quantity);
+            return true;
+        }
+        false
+    }
+
+    /// Adds the whole quantity or nothing.
+    pub fn try_add(&mut self, item: Item, quantity: u32) -> Result<(), InventoryFull> {
+        let available = self.max_to_add(&item, quantity);
+        if available < quantity {
+            return Err(InventoryFull {
+                requested: quantity,
+                available,
+            });
+        }
+        self.insert(item, quantity);
+        Ok(())
+    }
+
+    pub fn quantity_of(&self, item_id: &ItemId) -> u32 {
+        self.slots
+            .iter()
+            .filter(|s| &s.item.id == item_id)
+            .map(|s| s.quantity)
+            .sum()
+    }
+
+    /// Removes up to `quantity` units, taken from every stack of the item in
+    /// slot order, and returns them as one stack, if any.
+    pub fn remove(&mut self, item_id: &ItemId, quantity: u32) -> Option<ItemStack> {
+        let mut removed = 0;
+        let mut item = None;
+        for stack in self.slots.iter_mut().filter(|s| &s.item.id == item_id) {
+            if removed == quantity {
+                break;
+            }
+            removed += stack.remove(quantity - removed);
+            item.get_or_insert_with(|| stack.item.clone());
+        }
+        self.slots.retain(|s| s.quantity > 0);
+        item.filter(|_| removed > 0)
+            .map(|item| ItemStack::new(item, removed))
+    }
+
+    /// Moves up to `quantity` units of `item_id` from `from` to `to`.
+    /// The transfer is all-or-nothing: if `to` cannot hold what `from`
+    /// provides, neither inventory changes. Returns the moved quantity and
+    /// records a `ComponentChange` on each side when something moved.
+    pub fn try_transfer(
+        from: &mut Inventory,
+        to: &mut Inventory,
+        item_id: &ItemId,
+        quantity: u32,
+    ) -> Result<u32, InventoryFull> {
+        let Some(stack) = from.slots.iter().find(|s| &s.item.id == item_id) else {
+            return Ok(0);
+        };
+        let moved = quantity.min(from.quantity_of(item_id));
+        let available = to.max_to_add(&stack.item, moved);
+        if available < moved {
+            return Err(InventoryFull {
+                requested: moved,
+                available,
+            });
+        }
+        let Some(removed) = from.remove(item_id, moved) else {
+            return Ok(0);
+        };
+        let moved = removed.quantity;
+        to.insert(removed.item, moved);
+        Self::record_transfer(from, to, item_id, moved);
+        Ok(moved)
+    }
+
+    /// Moves as many of `quantity` units of `item_id` as `to` can hold.
+    /// Returns the remainder left in `from` out of that request (0 when
+    /// everything moved, including when `from` had fewer units to give).
+    pub fn transfer_max(
+        from: &mut Inventory,
+        to: &mut Inventory,
+        item_id: &ItemId,
+        quantity: u32,
+    ) -> u32 {
+        let Some(stack) = from.slots.iter().find(|s| &s.item.id == item_id) else {
+            return 0;
+        };
+        let wanted = quantity.min(from.quantity_of(item_id));
+        let fits = to.max_to_add(&stack.item, wanted);
+        let moved = match from.remove(item_id, fits) {
+            Some(removed) => {
+                let moved = removed.quantity;
+                to.insert(removed.item, moved);
+                moved
             }
+            None => 0,
+        };
+        Self::record_transfer(from, to, item_id, moved);
+        wanted - moved
+    }
+
+    fn insert(&mut self, item: Item, quantity: u32) {
+        if let Some(stack) = self.slots.iter_mut().find(|s| s.item.id == item.id) {
+            stack.add(quantity);
+        } else {
             // Otherwise, create a new stack
             self.slots.push(ItemStack::new(item, quantity));
-            return true;
         }
-        false
     }
 
     pub fn add_max(&mut self, item: Item, quantity: u32) -> bool {
@@ -68,3 +204,104 @@ impl Inventory {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 1 kg and 1 m³ per unit, so capacities read as unit counts
+    fn ore() -> Item {
+        Item::new(1, "Iron Ore".to_string(), 1.0, 1.0)
+    }
+
+    fn with_ore(max: f32, quantity: u32) -> Inventory {
+        let mut inventory = Inventory::new(max, max);
+        inventory.try_add(ore(), quantity).unwrap();
+        inventory
+    }
+
+    fn ore_change(delta: i64) -> ComponentChange {
+        ComponentChange::Inventory {
+            item_id: ore().id,
+            delta,
+        }
+    }
+
+    #[test]
+    fn transfer_that_fits() {
+        let (mut from, mut to) = (with_ore(100.0, 30), Inventory::new(100.0, 100.0));
+        let moved = Inventory::try_transfer(&mut from, &mut to, &ore().id, 20);
+        assert_eq!(moved, Ok(20));
+        assert_eq!(from.quantity_of(&ore().id), 10);
+        assert_eq!(to.quantity_of(&ore().id), 20);
+        assert_eq!(to.current_volume(), 20.0);
+        assert_eq!(from.take_changes(), vec![ore_change(-20)]);
+        assert_eq!(to.take_changes(), vec![ore_change(20)]);
+        assert!(to.take_changes().is_empty());
+    }
+
+    #[test]
+    fn transfer_that_partially_fits() {
+        let (mut from, mut to) = (with_ore(100.0, 30), with_ore(25.0, 10));
+        // try_transfer leaves both inventories untouched
+        let err = Inventory::try_transfer(&mut from, &mut to, &ore().id, 20);
+        assert_eq!(
+            err,
+            Err(InventoryFull {
+                requested: 20,
+                available: 15
+            })
+        );
+        assert_eq!(from.quantity_of(&ore().id), 30);
+        assert!(from.take_changes().is_empty());
+        assert!(to.take_changes().is_empty());
+
+        // transfer_max moves what fits and reports the rest
+        let remainder = Inventory::transfer_max(&mut from, &mut to, &ore().id, 20);
+        assert_eq!(remainder, 5);
+        assert_eq!(from.quantity_of(&ore().id), 15);
+        assert_eq!(to.quantity_of(&ore().id), 25);
+        assert_eq!(from.take_changes(), vec![ore_change(-15)]);
+        assert_eq!(to.take_changes(), vec![ore_change(15)]);
+    }
+
+    #[test]
+    fn transfer_rejected_when_target_full() {
+        let (mut from, mut to) = (with_ore(100.0, 30), with_ore(10.0, 10));
+        assert!(Inventory::try_transfer(&mut from, &mut to, &ore().id, 5).is_err());
+        assert_eq!(Inventory::transfer_max(&mut from, &mut to, &ore().id, 5), 5);
+        assert_eq!(from.quantity_of(&ore().id), 30);
+        assert_eq!(to.quantity_of(&ore().id), 10);
+        assert!(from.take_changes().is_empty());
+        assert!(to.take_changes().is_empty());
+    }
+
+    #[test]
+    fn try_add_is_all_or_nothing() {
+        let mut inventory = with_ore(10.0, 8);
+        assert_eq!(
+            inventory.try_add(ore(), 3),
+            Err(InventoryFull {
+                requested: 3,
+                available: 2
+            })
+        );
+        assert_eq!(inventory.quantity_of(&ore().id), 8);
+    }
+
+    #[test]
+    fn remove_spans_all_stacks() {
+        let mut inventory = Inventory::new(100.0, 100.0);
+        inventory.slots.push(ItemStack::new(ore(), 4));
+        inventory.slots.push(ItemStack::new(ore(), 6));
+
+        let removed = inventory.remove(&ore().id, 7).unwrap();
+        assert_eq!(removed.quantity, 7);
+        assert_eq!(inventory.quantity_of(&ore().id), 3);
+        assert_eq!(inventory.slots.len(), 1);
+
+        assert_eq!(inventory.remove(&ore().id, 10).unwrap().quantity, 3);
+        assert!(inventory.slots.is_empty());
+        assert!(inventory.remove(&ore().id, 1).is_none());
+    }
+}
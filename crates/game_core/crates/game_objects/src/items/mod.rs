@@ -1,3 +1,5 @@
 pub mod items;
+pub mod registry;
 
 pub use items::*;
+pub use registry::*;
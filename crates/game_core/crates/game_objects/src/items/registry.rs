@@ -0,0 +1,113 @@
+use crate::items::{Item, ItemId, ItemStack};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Static data shared by every unit of an item type.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ItemDef {
+    pub id: ItemId,
+    pub name: String,
+    pub mass_per_unit: f32,   // in kg
+    pub volume_per_unit: f32, // in cubic meters
+    pub stack_size: u32,
+}
+
+impl ItemDef {
+    pub fn new(
+        id: u32,
+        name: impl Into<String>,
+        mass_per_unit: f32,
+        volume_per_unit: f32,
+        stack_size: u32,
+    ) -> Self {
+        Self {
+            id: ItemId(id),
+            name: name.into(),
+            mass_per_unit,
+            volume_per_unit,
+            stack_size,
+        }
+    }
+
+    pub fn to_item(&self) -> Item {
+        Item {
+            id: self.id,
+            name: self.name.clone(),
+            mass_per_unit: self.mass_per_unit,
+            volume_per_unit: self.volume_per_unit,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ItemRegistry {
+    defs: HashMap<ItemId, ItemDef>,
+}
+
+impl ItemRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds a registry from already-deserialized definitions.
+    /// Later definitions replace earlier ones with the same id.
+    pub fn from_defs(defs: impl IntoIterator<Item = ItemDef>) -> Self {
+        let mut registry = Self::new();
+        for def in defs {
+            registry.register(def);
+        }
+        registry
+    }
+
+    /// Base ores, ingots and components.
+    pub fn with_defaults() -> Self {
+        Self::from_defs([
+            ItemDef::new(1, "Iron Ore", 1.0, 0.00037, 1_000_000),
+            ItemDef::new(2, "Nickel Ore", 1.0, 0.00037, 1_000_000),
+            ItemDef::new(3, "Silicon Ore", 1.0, 0.00037, 1_000_000),
+            ItemDef::new(4, "Ice", 1.0, 0.00037, 1_000_000),
+            ItemDef::new(10, "Iron Ingot", 1.0, 0.000127, 1_000_000),
+            ItemDef::new(11, "Nickel Ingot", 1.0, 0.000112, 1_000_000),
+            ItemDef::new(12, "Silicon Wafer", 1.0, 0.000429, 1_000_000),
+            ItemDef::new(20, "Steel Plate", 20.0, 0.003, 1_000),
+            ItemDef::new(21, "Interior Plate", 3.0, 0.005, 1_000),
+            ItemDef::new(22, "Construction Component", 8.0, 0.002, 1_000),
+            ItemDef::new(23, "Metal Grid", 6.0, 0.015, 1_000),
+            ItemDef::new(24, "Small Steel Tube", 4.0, 0.002, 1_000),
+            ItemDef::new(25, "Large Steel Tube", 25.0, 0.038, 1_000),
+            ItemDef::new(26, "Motor", 24.0, 0.008, 1_000),
+            ItemDef::new(27, "Computer", 0.2, 0.001, 1_000),
+        ])
+    }
+
+    pub fn register(&mut self, def: ItemDef) {
+        self.defs.insert(def.id, def);
+    }
+
+    pub fn get(&self, id: ItemId) -> Option<&ItemDef> {
+        self.defs.get(&id)
+    }
+
+    pub fn item(&self, id: ItemId) -> Option<Item> {
+        self.get(id).map(ItemDef::to_item)
+    }
+
+    pub fn len(&self) -> usize {
+        self.defs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.defs.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ItemDef> {
+        self.defs.values()
+    }
+}
+
+impl ItemStack {
+    /// Creates a stack whose per-unit mass and volume come from the registry.
+    pub fn from_registry(id: ItemId, quantity: u32, registry: &ItemRegistry) -> Option<Self> {
+        registry.item(id).map(|item| Self::new(item, quantity))
+    }
+}
@@ -1,3 +1,6 @@
+// Chaque module expose un fichier du même nom (grids/grids.rs...), ré-exporté par son mod.rs
+#![allow(clippy::module_inception)]
+
 pub mod blocks;
 pub mod camera;
 pub mod celestials;
@@ -1,7 +1,10 @@
+use crate::physics::metrics::FloatPosition;
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
 
 /* -------------------- Bounds -------------------- */
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct RectBoundaries {
     pub x_min: i32,
     pub x_max: i32,
@@ -23,7 +26,7 @@ impl RectBoundaries {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct CircleBoundaries {
     pub radius: f32,
 }
@@ -33,7 +36,7 @@ impl CircleBoundaries {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum Boundaries {
     Rect(RectBoundaries),
     Circle(CircleBoundaries),
@@ -45,4 +48,42 @@ impl Boundaries {
             Boundaries::Circle(_) => "Circular",
         }
     }
-}
\ No newline at end of file
+
+    /// Boîte englobante (min, max) en espace monde ; les bornes sont relatives à `pos`
+    pub fn aabb(&self, pos: &FloatPosition) -> (Vec3, Vec3) {
+        let p = pos.to_vec3();
+        match self {
+            Boundaries::Rect(r) => (
+                p + Vec3::new(r.x_min as f32, r.y_min as f32, r.z_min as f32),
+                p + Vec3::new(r.x_max as f32, r.y_max as f32, r.z_max as f32),
+            ),
+            Boundaries::Circle(c) => (p - Vec3::splat(c.radius), p + Vec3::splat(c.radius)),
+        }
+    }
+
+    /// Test de recouvrement en espace monde (contact inclus)
+    pub fn intersects(
+        &self,
+        other: &Boundaries,
+        a_pos: &FloatPosition,
+        b_pos: &FloatPosition,
+    ) -> bool {
+        match (self, other) {
+            (Boundaries::Circle(a), Boundaries::Circle(b)) => {
+                let r = a.radius + b.radius;
+                a_pos.to_vec3().distance_squared(b_pos.to_vec3()) <= r * r
+            }
+            (Boundaries::Rect(_), Boundaries::Rect(_)) => {
+                let (a_min, a_max) = self.aabb(a_pos);
+                let (b_min, b_max) = other.aabb(b_pos);
+                a_min.cmple(b_max).all() && b_min.cmple(a_max).all()
+            }
+            (Boundaries::Rect(_), Boundaries::Circle(c)) => {
+                let (min, max) = self.aabb(a_pos);
+                let center = b_pos.to_vec3();
+                center.clamp(min, max).distance_squared(center) <= c.radius * c.radius
+            }
+            (Boundaries::Circle(_), Boundaries::Rect(_)) => other.intersects(self, b_pos, a_pos),
+        }
+    }
+}
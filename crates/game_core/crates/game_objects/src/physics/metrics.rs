@@ -1,9 +1,19 @@
-
 use glam::{Quat, Vec3};
+use serde::{Deserialize, Serialize};
 
 /* -------------------- Positions -------------------- */
 
-#[derive(Debug, Clone)]
+/// Arête d'une cellule de grille, en mètres : 2.5 (large) ou 0.5 (small)
+#[inline]
+pub fn cell_size(is_large: bool) -> f32 {
+    if is_large {
+        2.5
+    } else {
+        0.5
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FloatPosition {
     pub x: f32,
     pub y: f32,
@@ -16,15 +26,30 @@ impl FloatPosition {
     pub fn zero() -> Self {
         Self::new(0.0, 0.0, 0.0)
     }
+    /// Position « non définie » (NaN), comme `MapId::undefined`. Se relit telle
+    /// quelle en bincode (bits f32 copiés) ; un format texte comme JSON n'a pas
+    /// de NaN et ne peut pas l'encoder.
+    pub fn undefined() -> Self {
+        Self::new(f32::NAN, f32::NAN, f32::NAN)
+    }
+    /// Vrai si une coordonnée est NaN (`==` ne le détecte pas : NaN != NaN)
+    pub fn is_undefined(&self) -> bool {
+        self.x.is_nan() || self.y.is_nan() || self.z.is_nan()
+    }
     pub fn to_vec3(&self) -> Vec3 {
         Vec3::new(self.x, self.y, self.z)
     }
+    /// Arrondi vers -inf (et non vers 0) pour rester cohérent avec `div_floor` du mapping
     pub fn to_int_position(&self) -> IntPosition {
-        IntPosition::new(self.x as i32, self.y as i32, self.z as i32)
+        IntPosition::new(
+            self.x.floor() as i32,
+            self.y.floor() as i32,
+            self.z.floor() as i32,
+        )
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct IntPosition {
     pub x: i32,
     pub y: i32,
@@ -40,18 +65,23 @@ impl IntPosition {
     pub fn to_vec3(&self) -> Vec3 {
         Vec3::new(self.x as f32, self.y as f32, self.z as f32)
     }
-    pub fn to_world_position(&self, grid_position: &FloatPosition) -> FloatPosition {
+    pub fn to_world_position(
+        &self,
+        grid_position: &FloatPosition,
+        is_large: bool,
+    ) -> FloatPosition {
+        let cell = cell_size(is_large);
         FloatPosition::new(
-            grid_position.x + self.x as f32 * 2.5,
-            grid_position.y + self.y as f32 * 2.5,
-            grid_position.z + self.z as f32 * 2.5,
+            grid_position.x + self.x as f32 * cell,
+            grid_position.y + self.y as f32 * cell,
+            grid_position.z + self.z as f32 * cell,
         )
     }
 }
 
 /* -------------------- Metrics -------------------- */
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IntPositionDelta {
     pub delta_x: i32,
     pub delta_y: i32,
@@ -69,7 +99,7 @@ impl IntPositionDelta {
         Self::new(0, 0, 0)
     }
     pub fn to_vec3(&self) -> Vec3 {
-        Self::to_float_delta(&self).to_vec3()
+        Self::to_float_delta(self).to_vec3()
     }
     pub fn between(a: &IntPosition, b: &IntPosition) -> Self {
         Self {
@@ -93,7 +123,7 @@ impl IntPositionDelta {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FloatPositionDelta {
     pub delta_x: f32,
     pub delta_y: f32,
@@ -135,7 +165,7 @@ impl FloatPositionDelta {
 
 /* -------------------- Kinematics -------------------- */
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Velocity {
     pub x: f32,
     pub y: f32,
@@ -150,7 +180,7 @@ impl Velocity {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Acceleration {
     pub x: f32,
     pub y: f32,
@@ -167,7 +197,7 @@ impl Acceleration {
 
 /* -------------------- Orientation -------------------- */
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FloatOrientation {
     pub pitch: f32,
     pub yaw: f32,
@@ -185,7 +215,7 @@ impl FloatOrientation {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FloatOrientationDelta {
     pub delta_pitch: f32,
     pub delta_yaw: f32,
@@ -221,7 +251,7 @@ impl FloatOrientationDelta {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct IntOrientation {
     pub pitch: i32,
     pub yaw: i32,
@@ -244,7 +274,7 @@ impl IntOrientation {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IntOrientationDelta {
     pub delta_pitch: f32,
     pub delta_yaw: f32,
@@ -278,4 +308,45 @@ impl IntOrientationDelta {
             self.delta_roll as i32,
         )
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_int_position_floors_negative_coordinates() {
+        // -0.1 tombe dans la cellule -1, pas 0 (un `as i32` tronquerait vers 0)
+        let p = FloatPosition::new(-0.1, 0.1, -2.0);
+        assert_eq!(p.to_int_position(), IntPosition::new(-1, 0, -2));
+    }
+
+    #[test]
+    fn to_world_position_scales_by_grid_size() {
+        assert_eq!(cell_size(false), 0.5);
+        assert_eq!(cell_size(true), 2.5);
+
+        let grid = FloatPosition::new(10.0, 0.0, -4.0);
+        let cell = IntPosition::new(2, -1, 3);
+        let small = cell.to_world_position(&grid, false);
+        assert_eq!((small.x, small.y, small.z), (11.0, -0.5, -2.5));
+        let large = cell.to_world_position(&grid, true);
+        assert_eq!((large.x, large.y, large.z), (15.0, -2.5, 3.5));
+    }
+
+    fn round_trip(p: &FloatPosition) -> FloatPosition {
+        bincode::deserialize(&bincode::serialize(p).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn undefined_position_survives_bincode() {
+        let p = round_trip(&FloatPosition::new(1.5, -2.0, 3.25));
+        assert!(!p.is_undefined());
+        assert_eq!((p.x, p.y, p.z), (1.5, -2.0, 3.25));
+
+        // Revient en NaN, à tester avec is_undefined() et non par égalité
+        let undefined = round_trip(&FloatPosition::undefined());
+        assert!(undefined.is_undefined());
+        assert!(undefined.x.is_nan() && undefined.y.is_nan() && undefined.z.is_nan());
+    }
+}
@@ -4,10 +4,11 @@ use crate::physics::boundaries::Boundaries;
 use crate::physics::metrics::{Acceleration, FloatOrientation, FloatPosition, Velocity};
 use crate::utils::ids::MapId;
 use crate::utils::mapping::pos_to_map_id;
+use serde::{Deserialize, Serialize};
 
 /* -------------------- Physical object -------------------- */
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PhysicalObject {
     pub timestamp: Option<u64>,
     pub position: Option<FloatPosition>,
@@ -21,6 +22,7 @@ pub struct PhysicalObject {
 }
 
 impl PhysicalObject {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         timestamp: Option<u64>,
         position: Option<FloatPosition>,
@@ -65,6 +67,31 @@ impl PhysicalObject {
         merged
     }
 
+    /// Euler semi-implicite : v += a·dt puis p += v·dt. Enregistre un delta
+    /// (appliqué par `compute_and_apply_pending_deltas`), rien si pas de vitesse.
+    pub fn integrate(&mut self, dt: f32, timestamp: Option<u64>) {
+        let (Some(p), Some(v)) = (self.position.as_ref(), self.velocity.as_ref()) else {
+            return;
+        };
+        let mut v = v.clone();
+        if let Some(ref a) = self.acceleration {
+            v.x += a.x * dt;
+            v.y += a.y * dt;
+            v.z += a.z * dt;
+        }
+        let p = FloatPosition::new(p.x + v.x * dt, p.y + v.y * dt, p.z + v.z * dt);
+        self.record_delta(PhysicalObjectDelta {
+            timestamp,
+            position: Some(p),
+            orientation: None,
+            velocity: Some(v),
+            acceleration: None,
+            mass: None,
+            boundaries: None,
+            map_id: None,
+        });
+    }
+
     pub fn update_map_id(&mut self) {
         if let Some(ref pos) = self.position {
             let ip = pos.to_int_position();
@@ -74,7 +101,7 @@ impl PhysicalObject {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PhysicalObjectDelta {
     pub timestamp: Option<u64>,
     pub position: Option<FloatPosition>,
@@ -141,7 +168,7 @@ impl PhysicalObjectDelta {
         if let Some(m) = self.mass {
             e.mass = Some(m);
         }
-        if let Some(b) = self.boundaries.clone() {
+        if let Some(b) = self.boundaries {
             e.boundaries = Some(b);
         }
         if let Some(id) = self.map_id {
@@ -6,9 +6,11 @@ use crate::entities::Entity;
 use crate::utils::ids::EntityId;
 
 use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::sync::{
     atomic::{AtomicU32, Ordering},
-    Arc, RwLock,
+    Arc, Mutex, RwLock,
 };
 
 // --- vues multiples: toutes en EntityId --------------------------------------
@@ -22,10 +24,27 @@ pub struct IdLists {
     pub block_ids: Vec<EntityId>,
 }
 
-// Compteur d'EntityId (atomic)
+// Compteur d'EntityId (atomic) + ids libérés, réutilisés du plus petit au plus grand
+// (ordre indépendant de l'ordre des suppressions => déterministe)
 #[derive(Default)]
 pub struct IdCounters {
     pub entity: AtomicU32,
+    pub free_entity: Mutex<BinaryHeap<Reverse<u32>>>,
+}
+
+impl IdCounters {
+    #[inline]
+    pub fn alloc_entity(&self) -> u32 {
+        if let Some(Reverse(v)) = self.free_entity.lock().unwrap().pop() {
+            return v;
+        }
+        self.entity.fetch_add(1, Ordering::Relaxed)
+    }
+
+    #[inline]
+    pub fn release_entity(&self, v: u32) {
+        self.free_entity.lock().unwrap().push(Reverse(v));
+    }
 }
 
 #[inline]
@@ -63,12 +82,20 @@ macro_rules! define_arenas {
                     counters: Default::default(),
                 }
             }
+        }
+
+        impl Default for Arenas {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl Arenas {
 
             $(
                 #[inline]
                 pub fn $alloc_id_fn(&self) -> $id {
-                    let v = self.counters.entity.fetch_add(1, Ordering::Relaxed);
-                    $id(v)
+                    $id(self.counters.alloc_entity())
                 }
 
                 #[inline]
@@ -101,7 +128,11 @@ macro_rules! define_arenas {
 
                 #[inline]
                 pub fn $remove_fn(&mut self, id: $id) -> Option<$ty> {
-                    self.$field.remove(id)
+                    let removed = self.$field.remove(id);
+                    if removed.is_some() {
+                        self.counters.release_entity(id.0);
+                    }
+                    removed
                 }
             )+
 
@@ -127,10 +158,52 @@ define_arenas! {
         remove_fn = remove_entity,
 }
 
+// ==== itération par catégorie =================================================
+impl Arenas {
+    /// Alloue un EntityId tagué entité + physique. Reprend d'abord le plus petit
+    /// id libéré, sinon le compteur : même suite de spawn/remove => mêmes ids.
+    ///
+    /// Attention : un id libéré est réattribué au spawn suivant. Un client qui le
+    /// tient encore (snapshot en vol, cible sélectionnée) désignera alors la
+    /// nouvelle entité ; le retrait doit lui parvenir avant tout recyclage.
+    pub fn alloc_physical_entity_id(&mut self) -> EntityId {
+        let id = self.alloc_entity_id();
+        self.tag_entity(id);
+        self.tag_physical(id);
+        id
+    }
+
+    /// Itère les entités physiques taguées (ids absents ignorés)
+    pub fn iter_physical_entities(&self) -> impl Iterator<Item = (EntityId, &ArcRw<Entity>)> + '_ {
+        self.entities.get_many(&self.lists.physical_entity_ids)
+    }
+
+    /// Itère les entités logiques taguées (ids absents ignorés)
+    pub fn iter_logical_entities(&self) -> impl Iterator<Item = (EntityId, &ArcRw<Entity>)> + '_ {
+        self.entities.get_many(&self.lists.logical_entity_ids)
+    }
+
+    /// Applique `f` à chaque entité physique, verrou en écriture pris une par une
+    pub fn for_each_physical_entity_mut(&self, mut f: impl FnMut(EntityId, &mut Entity)) {
+        for (id, e) in self.iter_physical_entities() {
+            let mut g = e.write().unwrap();
+            f(id, &mut g);
+        }
+    }
+
+    /// Applique `f` à chaque entité logique, verrou en écriture pris une par une
+    pub fn for_each_logical_entity_mut(&self, mut f: impl FnMut(EntityId, &mut Entity)) {
+        for (id, e) in self.iter_logical_entities() {
+            let mut g = e.write().unwrap();
+            f(id, &mut g);
+        }
+    }
+}
+
 // ==== TLS handle ==============================================================
 pub type SharedArenas = Arc<RwLock<Arenas>>;
 
-thread_local! { static ARENAS_STACK: RefCell<Vec<SharedArenas>> = RefCell::new(Vec::new()); }
+thread_local! { static ARENAS_STACK: RefCell<Vec<SharedArenas>> = const { RefCell::new(Vec::new()) }; }
 
 pub struct ArenasScope;
 impl Drop for ArenasScope {
@@ -159,7 +232,7 @@ pub fn with_current_write<R>(f: impl FnOnce(&mut Arenas) -> R) -> R {
     ARENAS_STACK.with(|s| {
         let h = s.borrow().last().cloned().expect("No current Arenas scope");
         let mut g = h.write().unwrap();
-        f(&mut *g)
+        f(&mut g)
     })
 }
 #[inline]
@@ -167,10 +240,88 @@ pub fn with_current_read<R>(f: impl FnOnce(&Arenas) -> R) -> R {
     ARENAS_STACK.with(|s| {
         let h = s.borrow().last().cloned().expect("No current Arenas scope");
         let g = h.read().unwrap();
-        f(&*g)
+        f(&g)
     })
 }
 #[inline]
 pub fn current_handle() -> Option<SharedArenas> {
     ARENAS_STACK.with(|s| s.borrow().last().cloned())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::humanoids::Humanoid;
+    use crate::physics::{FloatPosition, PhysicalObject};
+    use crate::world::World;
+
+    fn at(x: f32) -> PhysicalObject {
+        PhysicalObject::new(
+            None,
+            Some(FloatPosition::new(x, 0.0, 0.0)),
+            None,
+            None,
+            None,
+            Some(1.0),
+            None,
+            None,
+            Vec::new(),
+        )
+    }
+
+    fn sum_x(a: &Arenas) -> f32 {
+        a.iter_physical_entities()
+            .filter_map(|(_, e)| {
+                let g = e.read().unwrap();
+                g.physical_object()
+                    .and_then(|po| po.position.as_ref().map(|p| p.x))
+            })
+            .sum()
+    }
+
+    #[test]
+    fn freed_ids_are_reused_smallest_first() {
+        let world = World::new(0, "test".into());
+        let _scope = world.scope();
+        let ids: Vec<_> = (0..4).map(|_| Humanoid::spawn(None, None)).collect();
+        assert_eq!(ids, [0, 1, 2, 3].map(EntityId));
+
+        // Ordre de suppression sans effet sur l'ordre de réutilisation
+        assert!(Humanoid::remove(ids[2]));
+        assert!(Humanoid::remove(ids[0]));
+
+        let mut a = world.arenas.write().unwrap();
+        assert_eq!(a.alloc_physical_entity_id(), EntityId(0));
+        assert_eq!(a.alloc_physical_entity_id(), EntityId(2));
+        // Tas vide : le compteur reprend après le plus grand id émis
+        assert_eq!(a.alloc_physical_entity_id(), EntityId(4));
+        assert!(a.lists.physical_entity_ids.contains(&EntityId(4)));
+    }
+
+    #[test]
+    fn physical_iteration_sums_and_updates_tagged_entities() {
+        let world = World::new(0, "test".into());
+        {
+            let _scope = world.scope();
+            for x in [1.0, 2.0, 4.0] {
+                Humanoid::spawn(None, Some(at(x)));
+            }
+            // Taguée physique mais sans PhysicalObject
+            Humanoid::spawn(None, None);
+        }
+        let mut a = world.arenas.write().unwrap();
+        // Id tagué sans entité : ignoré
+        a.lists.physical_entity_ids.push(EntityId(999));
+        assert_eq!(sum_x(&a), 7.0);
+
+        let mut visited = 0;
+        a.for_each_physical_entity_mut(|_, e| {
+            visited += 1;
+            if let Some(p) = e.physical_object_mut().and_then(|po| po.position.as_mut()) {
+                p.x += 10.0;
+            }
+        });
+        assert_eq!(visited, 4);
+        assert_eq!(sum_x(&a), 37.0);
+    }
+}
@@ -44,6 +44,22 @@ pub fn pos_to_region(p: &IntPosition) -> RegionId {
     )
 }
 
+/// Origine (coin minimal) d'une cellule, d'un chunk ou d'une région en coordonnées monde
+#[inline]
+pub fn cell_origin(c: &CellId) -> IntPosition {
+    IntPosition::new(c.0 * CELL_SIZE, c.1 * CELL_SIZE, c.2 * CELL_SIZE)
+}
+
+#[inline]
+pub fn chunk_origin(c: &ChunkId) -> IntPosition {
+    IntPosition::new(c.0 * CHUNK_SIZE, c.1 * CHUNK_SIZE, c.2 * CHUNK_SIZE)
+}
+
+#[inline]
+pub fn region_origin(r: &RegionId) -> IntPosition {
+    IntPosition::new(r.0 * REGION_SIZE, r.1 * REGION_SIZE, r.2 * REGION_SIZE)
+}
+
 pub fn pos_to_map_id(ip: &IntPosition, m: &mut MapId) {
     let cell_id = pos_to_cell(ip);
     if m.cell_id == cell_id {
@@ -59,3 +75,79 @@ pub fn pos_to_map_id(ip: &IntPosition, m: &mut MapId) {
 
     m.region_id = pos_to_region(ip);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Positions autour de 0 et des bords négatifs, là où l'arrondi vers -inf compte
+    fn samples() -> Vec<IntPosition> {
+        let mut out = Vec::new();
+        for &v in &[
+            0,
+            1,
+            -1,
+            CELL_SIZE - 1,
+            CELL_SIZE,
+            -CELL_SIZE,
+            -CELL_SIZE - 1,
+            CHUNK_SIZE - 1,
+            -CHUNK_SIZE,
+            -CHUNK_SIZE - 1,
+            REGION_SIZE + 7,
+            -REGION_SIZE - 7,
+        ] {
+            out.push(IntPosition::new(v, -v, v / 3));
+        }
+        out
+    }
+
+    #[test]
+    fn cell_origin_round_trips() {
+        for p in samples() {
+            let c = pos_to_cell(&p);
+            let o = cell_origin(&c);
+            assert_eq!(pos_to_cell(&o), c);
+            for (v, min) in [(p.x, o.x), (p.y, o.y), (p.z, o.z)] {
+                assert!(
+                    min <= v && v < min + CELL_SIZE,
+                    "{v} hors de [{min}, +{CELL_SIZE})"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn chunk_origin_round_trips() {
+        for p in samples() {
+            let c = pos_to_chunk(&p);
+            let o = chunk_origin(&c);
+            assert_eq!(pos_to_chunk(&o), c);
+            for (v, min) in [(p.x, o.x), (p.y, o.y), (p.z, o.z)] {
+                assert!(min <= v && v < min + CHUNK_SIZE);
+            }
+        }
+    }
+
+    #[test]
+    fn region_origin_round_trips() {
+        for p in samples() {
+            let r = pos_to_region(&p);
+            let o = region_origin(&r);
+            assert_eq!(pos_to_region(&o), r);
+            for (v, min) in [(p.x, o.x), (p.y, o.y), (p.z, o.z)] {
+                assert!(min <= v && v < min + REGION_SIZE);
+            }
+        }
+    }
+
+    #[test]
+    fn negative_positions_floor_to_previous_cell() {
+        let p = IntPosition::new(-1, -CELL_SIZE, -CELL_SIZE - 1);
+        assert_eq!(pos_to_cell(&p), CellId(-1, -1, -2));
+        assert_eq!(
+            cell_origin(&pos_to_cell(&p)),
+            IntPosition::new(-CELL_SIZE, -CELL_SIZE, -2 * CELL_SIZE)
+        );
+    }
+}
@@ -1,12 +1,105 @@
 // world.rs — un monde possède son instance d’arènes
+use crate::entities::Entity;
+use crate::physics::{Boundaries, FloatPosition, PhysicalObject, PhysicalObjectDelta};
 use crate::utils::arenas::{Arenas, SharedArenas};
+use crate::utils::ids::EntityId;
+use rand::{rngs::StdRng, SeedableRng};
 use std::sync::{Arc, RwLock};
 
+/// Vue figée du monde à la fin d'un tick, partagée sans toucher aux arènes
+#[derive(Debug, Clone, Default)]
+pub struct BodiesSnapshot {
+    pub time: f64,
+    pub bodies: Vec<(EntityId, PhysicalObject)>,
+}
+
+/// FNV-1a 64 bits : stable d'une exécution et d'une machine à l'autre,
+/// contrairement au hasher aléatoire des `HashMap`
+struct StateHasher(u64);
+
+impl StateHasher {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= b as u64;
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn u32(&mut self, v: u32) {
+        self.bytes(&v.to_le_bytes());
+    }
+
+    fn u64(&mut self, v: u64) {
+        self.bytes(&v.to_le_bytes());
+    }
+
+    fn i32s(&mut self, vs: [i32; 3]) {
+        vs.iter().for_each(|&v| self.u32(v as u32));
+    }
+
+    /// Bits exacts : -0.0 et 0.0 diffèrent, ce qui suffit à repérer une désynchro
+    fn f32s(&mut self, vs: &[f32]) {
+        vs.iter().for_each(|&v| self.u32(v.to_bits()));
+    }
+
+    /// Préfixe présent/absent pour que `None` ne se confonde pas avec des zéros
+    fn opt_f32s<T>(&mut self, v: Option<&T>, f: impl FnOnce(&T) -> [f32; 3]) {
+        match v {
+            Some(v) => {
+                self.bytes(&[1]);
+                self.f32s(&f(v));
+            }
+            None => self.bytes(&[0]),
+        }
+    }
+
+    fn physical_object(&mut self, po: &PhysicalObject) {
+        self.opt_f32s(po.position.as_ref(), |p| [p.x, p.y, p.z]);
+        self.opt_f32s(po.orientation.as_ref(), |o| [o.pitch, o.yaw, o.roll]);
+        self.opt_f32s(po.velocity.as_ref(), |v| [v.x, v.y, v.z]);
+        self.opt_f32s(po.acceleration.as_ref(), |a| [a.x, a.y, a.z]);
+        self.opt_f32s(po.mass.as_ref(), |&m| [m, 0.0, 0.0]);
+    }
+
+    fn entity(&mut self, e: &Entity) {
+        match e {
+            Entity::Humanoid(_) => self.bytes(&[0]),
+            Entity::Celestial(c) => {
+                self.bytes(&[1]);
+                self.f32s(&[c.radius, c.gravity_strength]);
+            }
+            Entity::Grid(g) => {
+                self.bytes(&[2]);
+                self.u32(g.block_ids.len() as u32);
+                g.block_ids.iter().for_each(|id| self.u32(id.0));
+            }
+            Entity::Block(b) => {
+                self.bytes(&[3]);
+                self.u32(b.grid_id.0);
+                self.u64(b.def.id.unique_key());
+                self.f32s(&[b.current_integrity, b.current_mass]);
+                self.i32s([b.position.x, b.position.y, b.position.z]);
+                self.i32s([b.orientation.pitch, b.orientation.yaw, b.orientation.roll]);
+                self.u32(b.faction_id.0);
+            }
+        }
+        if let Some(po) = e.physical_object() {
+            self.physical_object(po);
+        }
+    }
+}
+
 pub struct World {
     pub seed: u64,
     pub name: String,
     pub time: f64,
     pub arenas: SharedArenas,
+    /// Double tampon : remplacé en bloc, jamais modifié en place
+    latest: RwLock<Arc<BodiesSnapshot>>,
 }
 
 impl World {
@@ -17,7 +110,114 @@ impl World {
             name,
             time: 0.0,
             arenas: Arc::new(RwLock::new(Arenas::new())),
+            latest: RwLock::new(Arc::new(BodiesSnapshot::default())),
+        }
+    }
+
+    /// RNG déterministe dérivé de la graine du monde.
+    /// `stream` sépare les usages (génération, IA...) pour qu'ils ne se décalent pas entre eux.
+    #[inline]
+    pub fn rng(&self, stream: u64) -> StdRng {
+        StdRng::seed_from_u64(self.seed ^ stream.wrapping_mul(0x9E37_79B9_7F4A_7C15))
+    }
+
+    /// Pas de physique à `dt` fixe : intègre chaque entité physique dans l'ordre
+    /// des listes de tagging (déterministe) et retourne les deltas appliqués.
+    /// Publie un nouvel instantané une fois le tick complet.
+    pub fn step_physics(&mut self, dt: f32) -> Vec<(EntityId, PhysicalObjectDelta)> {
+        self.time += dt as f64;
+        let timestamp = Some((self.time * 1000.0) as u64);
+        let mut applied = Vec::new();
+        {
+            let a = self.arenas.read().unwrap();
+            a.for_each_physical_entity_mut(|id, e| {
+                if let Some(po) = e.physical_object_mut() {
+                    po.integrate(dt, timestamp);
+                    if let Some(d) = po.compute_and_apply_pending_deltas() {
+                        po.update_map_id();
+                        applied.push((id, d));
+                    }
+                }
+            });
+        }
+        self.publish_snapshot();
+        applied
+    }
+
+    /// Reconstruit l'instantané depuis les arènes puis l'échange d'un coup :
+    /// un lecteur voit l'ancien ou le nouveau, jamais un tick à moitié appliqué.
+    pub fn publish_snapshot(&self) {
+        let bodies = {
+            let a = self.arenas.read().unwrap();
+            a.iter_physical_entities()
+                .filter_map(|(id, h)| {
+                    let g = h.read().unwrap();
+                    Some((id, g.physical_object()?.clone()))
+                })
+                .collect()
+        };
+        let snapshot = Arc::new(BodiesSnapshot {
+            time: self.time,
+            bodies,
+        });
+        *self.latest.write().unwrap() = snapshot;
+    }
+
+    /// Dernier instantané publié ; ne prend jamais le verrou des arènes
+    #[inline]
+    pub fn latest_snapshot(&self) -> Arc<BodiesSnapshot> {
+        self.latest.read().unwrap().clone()
+    }
+
+    /// Empreinte de l'état simulé (temps, entités triées par id, blocs, physique).
+    /// Deux exécutions depuis la même graine et les mêmes entrées donnent la même
+    /// valeur ; comparer celles du client et du serveur à un tick détecte une désynchro.
+    pub fn state_hash(&self) -> u64 {
+        let a = self.arenas.read().unwrap();
+        let mut entities: Vec<_> = a.entities.iter().collect();
+        entities.sort_by_key(|(id, _)| *id);
+
+        let mut h = StateHasher::new();
+        h.u64(self.time.to_bits());
+        h.u32(entities.len() as u32);
+        for (id, e) in entities {
+            h.u32(id.0);
+            h.entity(&e.read().unwrap());
+        }
+        h.0
+    }
+
+    /// Paires d'entités physiques en collision : balayage trié sur x des boîtes
+    /// englobantes, puis test exact via `Boundaries::intersects`.
+    pub fn collision_pairs(&self) -> Vec<(EntityId, EntityId)> {
+        let a = self.arenas.read().unwrap();
+        let mut bodies: Vec<(EntityId, FloatPosition, Boundaries, f32, f32)> = a
+            .iter_physical_entities()
+            .filter_map(|(id, h)| {
+                let g = h.read().unwrap();
+                let po = g.physical_object()?;
+                let (pos, b) = (po.position.clone()?, po.boundaries?);
+                let (min, max) = b.aabb(&pos);
+                Some((id, pos, b, min.x, max.x))
+            })
+            .collect();
+        bodies.sort_by(|l, r| l.3.total_cmp(&r.3));
+
+        let mut pairs = Vec::new();
+        for i in 0..bodies.len() {
+            for j in i + 1..bodies.len() {
+                // plus aucun recouvrement possible sur x
+                if bodies[j].3 > bodies[i].4 {
+                    break;
+                }
+                let (ia, pa, ba, _, _) = &bodies[i];
+                let (ib, pb, bb, _, _) = &bodies[j];
+                if ba.intersects(bb, pa, pb) {
+                    pairs.push((*ia, *ib));
+                }
+            }
         }
+        pairs
     }
 
     /// Ouvre une scope TLS liant *ce monde* comme courant.
@@ -26,3 +226,228 @@ impl World {
         crate::utils::arenas::enter_scope(self.arenas.clone())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::humanoids::Humanoid;
+    use crate::physics::{Acceleration, CircleBoundaries, RectBoundaries, Velocity};
+
+    fn body(pos: (f32, f32, f32), boundaries: Option<Boundaries>) -> PhysicalObject {
+        PhysicalObject::new(
+            None,
+            Some(FloatPosition::new(pos.0, pos.1, pos.2)),
+            None,
+            Some(Velocity::new(0.0, 0.0, 0.0)),
+            None,
+            Some(1.0),
+            boundaries,
+            None,
+            Vec::new(),
+        )
+    }
+
+    fn spawn(world: &World, pos: (f32, f32, f32), boundaries: Option<Boundaries>) -> EntityId {
+        let _scope = world.scope();
+        Humanoid::spawn(None, Some(body(pos, boundaries)))
+    }
+
+    fn circle(radius: f32) -> Option<Boundaries> {
+        Some(Boundaries::Circle(CircleBoundaries { radius }))
+    }
+
+    /// Cube centré d'arête 2 * `half`
+    fn rect(half: i32) -> Option<Boundaries> {
+        Some(Boundaries::Rect(RectBoundaries {
+            x_min: -half,
+            x_max: half,
+            y_min: -half,
+            y_max: half,
+            z_min: -half,
+            z_max: half,
+        }))
+    }
+
+    /// Paires d'un monde à deux corps, normalisées (plus petit id en premier)
+    fn pairs_of(
+        a: Option<Boundaries>,
+        b: Option<Boundaries>,
+        dx: f32,
+    ) -> Vec<(EntityId, EntityId)> {
+        let world = World::new(0, "test".into());
+        let ia = spawn(&world, (0.0, 0.0, 0.0), a);
+        let ib = spawn(&world, (dx, 0.5, 0.0), b);
+        let pairs: Vec<_> = world
+            .collision_pairs()
+            .into_iter()
+            .map(|(x, y)| (x.min(y), x.max(y)))
+            .collect();
+        for &(x, y) in &pairs {
+            assert_eq!((x, y), (ia.min(ib), ia.max(ib)));
+        }
+        pairs
+    }
+
+    #[test]
+    fn latest_snapshot_follows_ticks_in_order() {
+        let mut world = World::new(0, "test".into());
+        let first = {
+            let _scope = world.scope();
+            let mut po = body((0.0, 0.0, 0.0), None);
+            po.velocity = Some(Velocity::new(2.0, 0.0, 0.0));
+            Humanoid::spawn(None, Some(po))
+        };
+        let second = spawn(&world, (5.0, 0.0, 0.0), None);
+
+        // Rien de publié avant le premier tick
+        let initial = world.latest_snapshot();
+        assert_eq!(initial.time, 0.0);
+        assert!(initial.bodies.is_empty());
+
+        let mut previous = initial.clone();
+        for tick in 1..=3 {
+            world.step_physics(0.5);
+            let snapshot = world.latest_snapshot();
+            assert!(snapshot.time > previous.time);
+            assert_eq!(snapshot.time, tick as f64 * 0.5);
+
+            // Corps dans l'ordre de tagging, positions du tick complet
+            let ids: Vec<_> = snapshot.bodies.iter().map(|(id, _)| *id).collect();
+            assert_eq!(ids, vec![first, second]);
+            let x = snapshot.bodies[0].1.position.as_ref().unwrap().x;
+            assert_eq!(x, 2.0 * snapshot.time as f32);
+            previous = snapshot;
+        }
+
+        // Un instantané déjà lu n'est jamais modifié en place
+        assert!(initial.bodies.is_empty());
+    }
+
+    #[test]
+    fn constant_acceleration_matches_semi_implicit_euler() {
+        let (dt, accel, ticks) = (0.5, 2.0, 10);
+        let mut world = World::new(0, "test".into());
+        let id = {
+            let _scope = world.scope();
+            let mut po = body((0.0, 0.0, 0.0), None);
+            po.acceleration = Some(Acceleration::new(accel, 0.0, 0.0));
+            Humanoid::spawn(None, Some(po))
+        };
+        for _ in 0..ticks {
+            world.step_physics(dt);
+        }
+
+        // v_k = a·dt·k puis p_N = Σ v_k·dt = a·dt²·N(N+1)/2 (valeurs exactes en f32)
+        let n = ticks as f32;
+        let snapshot = world.latest_snapshot();
+        let (body_id, po) = &snapshot.bodies[0];
+        assert_eq!(*body_id, id);
+        assert_eq!(po.velocity.as_ref().unwrap().x, accel * dt * n);
+        assert_eq!(
+            po.position.as_ref().unwrap().x,
+            accel * dt * dt * n * (n + 1.0) / 2.0
+        );
+    }
+
+    /// Monde de test rempli de façon déterministe
+    fn populated() -> World {
+        let world = World::new(42, "test".into());
+        spawn(&world, (1.0, 2.0, 3.0), circle(1.0));
+        spawn(&world, (-4.0, 0.0, 8.0), rect(2));
+        world
+    }
+
+    fn draws(world: &World, stream: u64) -> Vec<u64> {
+        use rand::Rng;
+        let mut rng = world.rng(stream);
+        (0..8).map(|_| rng.random()).collect()
+    }
+
+    #[test]
+    fn rng_streams_depend_on_seed_and_stream() {
+        let (a, b) = (World::new(7, "a".into()), World::new(7, "b".into()));
+        // Même graine, même flux : même suite, quel que soit le monde
+        assert_eq!(draws(&a, 1), draws(&b, 1));
+        assert_eq!(draws(&a, 1), draws(&a, 1));
+
+        // Une autre graine ou un autre flux donne une autre suite
+        assert_ne!(draws(&a, 1), draws(&World::new(8, "a".into()), 1));
+        assert_ne!(draws(&a, 1), draws(&a, 2));
+        assert_ne!(draws(&a, 0), draws(&a, 1));
+    }
+
+    #[test]
+    fn state_hash_is_stable_for_equal_worlds() {
+        let (mut a, mut b) = (populated(), populated());
+        assert_eq!(a.state_hash(), a.state_hash());
+        assert_eq!(a.state_hash(), b.state_hash());
+
+        a.step_physics(0.1);
+        b.step_physics(0.1);
+        assert_eq!(a.state_hash(), b.state_hash());
+    }
+
+    #[test]
+    fn state_hash_changes_with_state() {
+        let world = populated();
+        let before = world.state_hash();
+
+        // Un seul bit de position suffit
+        {
+            let a = world.arenas.read().unwrap();
+            let id = a.lists.physical_entity_ids[0];
+            let h = a.get_entity(id).unwrap();
+            let mut g = h.write().unwrap();
+            let po = g.physical_object_mut().unwrap();
+            po.position.as_mut().unwrap().x += 0.001;
+        }
+        assert_ne!(world.state_hash(), before);
+
+        let other = populated();
+        spawn(&other, (0.0, 0.0, 0.0), None);
+        assert_ne!(other.state_hash(), populated().state_hash());
+
+        let mut later = populated();
+        later.time = 1.0;
+        assert_ne!(later.state_hash(), populated().state_hash());
+    }
+
+    #[test]
+    fn circle_circle_pairs() {
+        assert_eq!(pairs_of(circle(1.0), circle(1.0), 1.5).len(), 1);
+        assert!(pairs_of(circle(1.0), circle(1.0), 3.0).is_empty());
+    }
+
+    #[test]
+    fn rect_rect_pairs() {
+        assert_eq!(pairs_of(rect(1), rect(1), 1.5).len(), 1);
+        assert!(pairs_of(rect(1), rect(1), 2.5).is_empty());
+    }
+
+    #[test]
+    fn rect_circle_pairs() {
+        assert_eq!(pairs_of(rect(1), circle(1.0), 1.5).len(), 1);
+        assert!(pairs_of(rect(1), circle(1.0), 2.5).is_empty());
+        // Ordre inverse : le cercle est trié avant le rectangle sur x
+        assert_eq!(pairs_of(circle(1.0), rect(1), 1.5).len(), 1);
+        assert!(pairs_of(circle(1.0), rect(1), 2.5).is_empty());
+    }
+
+    #[test]
+    fn bodies_without_boundaries_never_collide() {
+        assert!(pairs_of(None, circle(1.0), 0.0).is_empty());
+    }
+
+    #[test]
+    fn collision_pairs_only_reads_entities() {
+        let world = World::new(0, "test".into());
+        let id = spawn(&world, (0.0, 0.0, 0.0), circle(1.0));
+        spawn(&world, (0.5, 0.0, 0.0), circle(1.0));
+
+        // Un lecteur concurrent de l'entité ne bloque pas la détection
+        let a = world.arenas.read().unwrap();
+        let h = a.get_entity(id).unwrap();
+        let _reader = h.read().unwrap();
+        assert_eq!(world.collision_pairs().len(), 1);
+    }
+}
@@ -1,6 +1,63 @@
-use game_core::objects::*;
+use bincode::Options;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+/// World position as sent on the wire
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Position {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Position {
+    pub fn new(x: f32, y: f32, z: f32) -> Self {
+        Self { x, y, z }
+    }
+}
+
+/// Block cell inside its grid, in cells
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct RelPosition {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl RelPosition {
+    pub fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+}
+
+/// Orientation as Euler angles in radians, applied X (pitch) then Y (yaw) then Z (roll)
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Orientation {
+    pub pitch: f32,
+    pub yaw: f32,
+    pub roll: f32,
+}
+
+impl Orientation {
+    pub fn new(pitch: f32, yaw: f32, roll: f32) -> Self {
+        Self { pitch, yaw, roll }
+    }
+}
+
+/// Block orientation inside its grid, same angle order as `Orientation`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct RelOrientation {
+    pub pitch: i32,
+    pub yaw: i32,
+    pub roll: i32,
+}
+
+impl RelOrientation {
+    pub fn new(pitch: i32, yaw: i32, roll: i32) -> Self {
+        Self { pitch, yaw, roll }
+    }
+}
+
 /// Protocol messages exchanged between client and server
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Message {
@@ -28,89 +85,255 @@ pub enum Message {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum PlayerAction {
-    UpdatePosition { position: Position },
+    /// `seq` increases with every update so the server can acknowledge it
+    UpdatePosition {
+        position: Position,
+        seq: u32,
+    },
     SpawnShip,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Leading byte of every encoded message; bump on any wire-incompatible change
+pub const PROTOCOL_VERSION: u8 = 2;
+/// Largest payload `decode` accepts. Length prefixes inside a payload are checked
+/// against the bytes actually received, so they cannot allocate past this either.
+pub const MAX_MESSAGE_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Bincode settings shared by `encode` and `decode`, spelled out so a dependency
+/// bump cannot silently change the wire format
+fn wire_options() -> impl Options {
+    bincode::DefaultOptions::new()
+        .with_little_endian()
+        .with_fixint_encoding()
+        .reject_trailing_bytes()
+}
+
+/// Serializes `message` for the wire: `PROTOCOL_VERSION` followed by the bincode payload
+pub fn encode(message: &Message) -> Vec<u8> {
+    let mut data = vec![PROTOCOL_VERSION];
+    wire_options()
+        .serialize_into(&mut data, message)
+        .expect("protocol messages always serialize");
+    data
+}
+
+/// Inverse of `encode`. Other versions, oversized, truncated or garbage payloads
+/// are errors.
+pub fn decode(data: &[u8]) -> anyhow::Result<Message> {
+    match data.split_first() {
+        // bincode ignores `with_limit` when reading from a slice, so check here
+        Some((&PROTOCOL_VERSION, payload)) if payload.len() as u64 > MAX_MESSAGE_SIZE => {
+            anyhow::bail!(
+                "message of {} bytes exceeds the {} byte limit",
+                payload.len(),
+                MAX_MESSAGE_SIZE
+            )
+        }
+        Some((&PROTOCOL_VERSION, payload)) => Ok(wire_options().deserialize(payload)?),
+        Some((&version, _)) => anyhow::bail!(
+            "unsupported protocol version {} (expected {})",
+            version,
+            PROTOCOL_VERSION
+        ),
+        None => anyhow::bail!("empty message"),
+    }
+}
+
+/// Full world state as seen by clients, keyed by player / ship id
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WorldSnapshot {
+    pub players: HashMap<u32, PlayerState>,
+    pub ships: HashMap<u32, ShipState>,
+}
+
+/// Difference between two snapshots (see `WorldSnapshot::diff`)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WorldDelta {
+    /// New or changed players, sent whole (they are small)
+    pub players_upserted: HashMap<u32, PlayerState>,
+    pub players_removed: Vec<u32>,
+    pub ships_added: HashMap<u32, ShipState>,
+    pub ships_changed: HashMap<u32, ShipDelta>,
+    pub ships_removed: Vec<u32>,
+}
+
+impl WorldDelta {
+    pub fn is_empty(&self) -> bool {
+        self.players_upserted.is_empty()
+            && self.players_removed.is_empty()
+            && self.ships_added.is_empty()
+            && self.ships_changed.is_empty()
+            && self.ships_removed.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShipDelta {
+    /// Each `Option` field is `Some` only when that value changed
+    pub name: Option<String>,
+    pub position: Option<Position>,
+    pub orientation: Option<Orientation>,
+    pub is_large: Option<bool>,
+    pub blocks_upserted: Vec<BlockState>,
+    pub blocks_removed: Vec<u32>,
+    pub version: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PlayerState {
     pub name: String,
     pub position: Position,
     pub health: f32,
+    /// Last `UpdatePosition::seq` the server processed (accepted or rejected)
+    pub last_input_seq: u32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ShipState {
     pub name: String,
     pub position: Position,
+    pub orientation: Orientation,
     pub blocks: Vec<BlockState>,
     pub version: u64,
+    /// Large grid (2.5 m cells) or small grid (0.5 m cells)
+    pub is_large: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct BlockState {
     pub id: u32,
     pub name: String,
     pub block_type: String,
     pub position: RelPosition,
+    pub orientation: RelOrientation,
     pub integrity: f32,
+    /// Owning faction, if any
+    pub faction_id: Option<u32>,
 }
 
 /// Network connection management using WebSockets
 pub mod connection {
     use super::*;
     use anyhow::Result;
-    use futures_util::{SinkExt, StreamExt};
+    use futures_util::{future, SinkExt, Stream, StreamExt};
     use std::collections::HashMap;
     use std::net::SocketAddr;
     use tokio::sync::mpsc;
     use tokio_tungstenite::{accept_async, connect_async, tungstenite::Message as WsMessage};
 
+    /// Default radius (world units) around a client within which it receives state
+    pub const DEFAULT_INTEREST_RADIUS: f32 = 1000.0;
+    /// Fastest legit movement (sprint on all axes) plus slack, in units per second
+    pub const MAX_PLAYER_SPEED: f32 = 25.0;
+    /// Absolute slack for position checks, absorbs network jitter
+    pub const POSITION_TOLERANCE: f32 = 1.0;
+    /// Actions accepted per client between two `reset_action_counts`
+    pub const MAX_ACTIONS_PER_TICK: u32 = 20;
+
     pub struct GameServer {
-        pub listener: tokio::net::TcpListener,
+        /// `None` for servers built with `in_memory`, or once a game loop took it
+        pub listener: Option<tokio::net::TcpListener>,
         pub connections: HashMap<u32, mpsc::UnboundedSender<Message>>,
         pub message_tx: mpsc::UnboundedSender<(u32, Message)>,
         pub message_rx: mpsc::UnboundedReceiver<(u32, Message)>,
         pub next_client_id: u32,
+        /// Last position reported by each client, used for interest management
+        pub client_positions: HashMap<u32, Position>,
+        pub interest_radius: f32,
+        /// When each client's position was last accepted
+        pub last_position_update: HashMap<u32, std::time::Instant>,
+        /// Actions received from each client during the current tick
+        pub action_counts: HashMap<u32, u32>,
     }
 
     impl GameServer {
         pub async fn new(addr: SocketAddr) -> Result<Self> {
             let listener = tokio::net::TcpListener::bind(addr).await?;
+            Ok(Self::with_listener(Some(listener)))
+        }
+
+        /// Server without a socket; clients attach through `connect_in_memory`
+        pub fn in_memory() -> Self {
+            Self::with_listener(None)
+        }
+
+        fn with_listener(listener: Option<tokio::net::TcpListener>) -> Self {
             let (message_tx, message_rx) = mpsc::unbounded_channel();
 
-            Ok(Self {
+            Self {
                 listener,
                 connections: HashMap::new(),
                 message_tx,
                 message_rx,
                 next_client_id: 1,
-            })
+                client_positions: HashMap::new(),
+                interest_radius: DEFAULT_INTEREST_RADIUS,
+                last_position_update: HashMap::new(),
+                action_counts: HashMap::new(),
+            }
         }
 
         pub async fn accept_connections(&mut self) -> Result<()> {
-            while let Ok((stream, addr)) = self.listener.accept().await {
-                tracing::info!("New connection from: {}", addr);
-
-                let client_id = self.next_client_id;
-                self.next_client_id += 1;
-
-                let tx = self.message_tx.clone();
-                let (conn_tx, conn_rx) = mpsc::unbounded_channel();
-                self.connections.insert(client_id, conn_tx);
-
-                // Spawn task to handle this WebSocket connection
-                tokio::spawn(async move {
-                    if let Err(e) =
-                        handle_websocket_connection(stream, client_id, tx, conn_rx).await
-                    {
-                        tracing::error!("Connection {} error: {}", client_id, e);
-                    }
-                });
+            let Some(listener) = self.listener.take() else {
+                return Ok(());
+            };
+            while let Ok((stream, addr)) = listener.accept().await {
+                self.register_connection(stream, addr);
             }
             Ok(())
         }
 
+        /// Allocates a client id and the channel carrying messages to that client
+        fn open_connection(&mut self) -> (u32, mpsc::UnboundedReceiver<Message>) {
+            let client_id = self.next_client_id;
+            self.next_client_id += 1;
+
+            let (conn_tx, conn_rx) = mpsc::unbounded_channel();
+            self.connections.insert(client_id, conn_tx);
+            (client_id, conn_rx)
+        }
+
+        /// Assigns a client id to an accepted stream and spawns its WebSocket task.
+        /// Lets a game loop accept connections itself (e.g. inside `tokio::select!`).
+        pub fn register_connection(
+            &mut self,
+            stream: tokio::net::TcpStream,
+            addr: SocketAddr,
+        ) -> u32 {
+            tracing::info!("New connection from: {}", addr);
+
+            let (client_id, conn_rx) = self.open_connection();
+            let tx = self.message_tx.clone();
+
+            // Spawn task to handle this WebSocket connection
+            tokio::spawn(async move {
+                if let Err(e) = handle_websocket_connection(stream, client_id, tx, conn_rx).await {
+                    tracing::error!("Connection {} error: {}", client_id, e);
+                }
+            });
+            client_id
+        }
+
+        /// Attaches a client through channels only, no socket or serialization.
+        /// Behaves like `register_connection` (including the final `Disconnect`
+        /// once the client is dropped) and must be called within a Tokio runtime.
+        pub fn connect_in_memory(&mut self) -> GameClient {
+            let (client_id, conn_rx) = self.open_connection();
+            let (tx, mut rx) = mpsc::unbounded_channel();
+
+            let incoming = futures_util::stream::poll_fn(move |cx| rx.poll_recv(cx));
+            tokio::spawn(forward_to_server(
+                client_id,
+                incoming,
+                self.message_tx.clone(),
+            ));
+
+            GameClient {
+                ws_tx: Some(tx),
+                message_rx: conn_rx,
+            }
+        }
+
         pub fn send_to_client(&self, client_id: u32, message: Message) -> Result<()> {
             if let Some(conn_tx) = self.connections.get(&client_id) {
                 conn_tx.send(message)?;
@@ -127,6 +350,84 @@ pub mod connection {
 
         pub fn disconnect_client(&mut self, client_id: u32) {
             self.connections.remove(&client_id);
+            self.client_positions.remove(&client_id);
+            self.last_position_update.remove(&client_id);
+            self.action_counts.remove(&client_id);
+        }
+
+        pub fn set_client_position(&mut self, client_id: u32, position: Position) {
+            self.client_positions.insert(client_id, position);
+            self.last_position_update
+                .insert(client_id, std::time::Instant::now());
+        }
+
+        /// Stores `position` if reachable from the last accepted one at `MAX_PLAYER_SPEED`.
+        /// A client with no known position yet is trusted; servers should seed it
+        /// with the spawn point on `Connect` so the first report is checked too.
+        pub fn accept_position(&mut self, client_id: u32, position: &Position) -> bool {
+            if let (Some(prev), Some(at)) = (
+                self.client_positions.get(&client_id),
+                self.last_position_update.get(&client_id),
+            ) {
+                let (dx, dy, dz) = (
+                    position.x - prev.x,
+                    position.y - prev.y,
+                    position.z - prev.z,
+                );
+                let max = MAX_PLAYER_SPEED * at.elapsed().as_secs_f32() + POSITION_TOLERANCE;
+                if dx * dx + dy * dy + dz * dz > max * max {
+                    return false;
+                }
+            }
+            self.set_client_position(client_id, *position);
+            true
+        }
+
+        /// Counts an action from `client_id` and returns its count for this tick
+        pub fn count_action(&mut self, client_id: u32) -> u32 {
+            let count = self.action_counts.entry(client_id).or_default();
+            *count += 1;
+            *count
+        }
+
+        /// Starts a new rate-limiting window; call once per server tick
+        pub fn reset_action_counts(&mut self) {
+            self.action_counts.clear();
+        }
+
+        /// Part of `world` within `interest_radius` of the client's last known position
+        /// (origin until it reports one). The client's own player is always included.
+        pub fn snapshot_for(&self, client_id: u32, world: &WorldSnapshot) -> WorldSnapshot {
+            let origin = Position::new(0.0, 0.0, 0.0);
+            let center = self.client_positions.get(&client_id).unwrap_or(&origin);
+            let r2 = self.interest_radius * self.interest_radius;
+            let in_range = |p: &Position| {
+                let (dx, dy, dz) = (p.x - center.x, p.y - center.y, p.z - center.z);
+                dx * dx + dy * dy + dz * dz <= r2
+            };
+
+            WorldSnapshot {
+                players: world
+                    .players
+                    .iter()
+                    .filter(|(&id, p)| id == client_id || in_range(&p.position))
+                    .map(|(&id, p)| (id, p.clone()))
+                    .collect(),
+                ships: world
+                    .ships
+                    .iter()
+                    .filter(|(_, s)| in_range(&s.position))
+                    .map(|(&id, s)| (id, s.clone()))
+                    .collect(),
+            }
+        }
+
+        /// Sends every client its own interest-filtered snapshot
+        pub fn broadcast_snapshots(&self, world: &WorldSnapshot) {
+            for (&client_id, conn_tx) in &self.connections {
+                let snapshot = self.snapshot_for(client_id, world);
+                let _ = conn_tx.send(Message::WorldSnapshot { snapshot });
+            }
         }
     }
 
@@ -158,11 +459,9 @@ pub mod connection {
             // Spawn task to send messages to server
             tokio::spawn(async move {
                 while let Some(message) = rx.recv().await {
-                    if let Ok(data) = bincode::serialize(&message) {
-                        let ws_msg = WsMessage::Binary(data);
-                        if ws_tx_sink.send(ws_msg).await.is_err() {
-                            break;
-                        }
+                    let ws_msg = WsMessage::Binary(encode(&message));
+                    if ws_tx_sink.send(ws_msg).await.is_err() {
+                        break;
                     }
                 }
             });
@@ -171,8 +470,11 @@ pub mod connection {
             tokio::spawn(async move {
                 while let Some(msg) = ws_rx_stream.next().await {
                     if let Ok(WsMessage::Binary(data)) = msg {
-                        if let Ok(message) = bincode::deserialize::<Message>(&data) {
-                            let _ = msg_tx.send(message);
+                        match decode(&data) {
+                            Ok(message) => {
+                                let _ = msg_tx.send(message);
+                            }
+                            Err(e) => tracing::warn!("Dropping server message: {}", e),
                         }
                     }
                 }
@@ -189,6 +491,12 @@ pub mod connection {
         }
     }
 
+    impl Default for GameClient {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
     pub async fn handle_websocket_connection(
         stream: tokio::net::TcpStream,
         client_id: u32,
@@ -196,86 +504,429 @@ pub mod connection {
         mut conn_rx: mpsc::UnboundedReceiver<Message>,
     ) -> Result<()> {
         let ws_stream = accept_async(stream).await?;
-        let (mut ws_tx, mut ws_rx) = ws_stream.split();
+        let (mut ws_tx, ws_rx) = ws_stream.split();
 
         // Spawn task to send messages to client
         tokio::spawn(async move {
             while let Some(message) = conn_rx.recv().await {
-                if let Ok(data) = bincode::serialize(&message) {
-                    let ws_msg = WsMessage::Binary(data);
-                    if ws_tx.send(ws_msg).await.is_err() {
-                        break;
-                    }
+                let ws_msg = WsMessage::Binary(encode(&message));
+                if ws_tx.send(ws_msg).await.is_err() {
+                    break;
                 }
             }
         });
 
-        // Receive messages from client
-        while let Some(msg) = ws_rx.next().await {
-            match msg {
-                Ok(WsMessage::Binary(data)) => {
-                    if let Ok(message) = bincode::deserialize::<Message>(&data) {
-                        server_tx.send((client_id, message))?;
-                    }
-                }
-                Ok(WsMessage::Close(_)) => break,
-                Err(_) => break,
-                _ => {}
+        // Receive messages from client until it closes or errors
+        let incoming = ws_rx
+            .take_while(|msg| future::ready(matches!(msg, Ok(m) if !m.is_close())))
+            .filter_map(|msg| {
+                future::ready(match msg {
+                    Ok(WsMessage::Binary(data)) => decode(&data)
+                        .map_err(|e| tracing::warn!("Dropping message from {}: {}", client_id, e))
+                        .ok(),
+                    _ => None,
+                })
+            });
+        forward_to_server(client_id, incoming, server_tx).await;
+        Ok(())
+    }
+
+    /// Tags each message from a client with its id and hands it to the game loop.
+    /// Shared by the WebSocket and in-memory transports.
+    async fn forward_to_server(
+        client_id: u32,
+        incoming: impl Stream<Item = Message>,
+        server_tx: mpsc::UnboundedSender<(u32, Message)>,
+    ) {
+        let mut incoming = std::pin::pin!(incoming);
+        while let Some(message) = incoming.next().await {
+            if server_tx.send((client_id, message)).is_err() {
+                return;
             }
         }
 
-        Ok(())
+        // Let the game loop drop the player even if the client never sent Disconnect
+        let _ = server_tx.send((client_id, Message::Disconnect));
     }
 }
 
-/// Utilities for converting between game_core types and protocol types
+/// Delta compression between two snapshots
 pub mod conversion {
     use super::*;
-    use game_core::world::GameWorld;
 
-    impl From<&Player> for PlayerState {
-        fn from(player: &Player) -> Self {
-            Self {
-                name: player.name.clone(),
-                position: player.physical.placed.position.clone(),
-                health: player.health,
+    impl WorldSnapshot {
+        /// Changes needed to turn `self` into `newer`
+        pub fn diff(&self, newer: &WorldSnapshot) -> WorldDelta {
+            let mut delta = WorldDelta::default();
+
+            for (&id, player) in &newer.players {
+                if self.players.get(&id) != Some(player) {
+                    delta.players_upserted.insert(id, player.clone());
+                }
             }
+            delta.players_removed = removed_ids(&self.players, &newer.players);
+
+            for (&id, ship) in &newer.ships {
+                match self.ships.get(&id) {
+                    None => {
+                        delta.ships_added.insert(id, ship.clone());
+                    }
+                    Some(old) if old != ship => {
+                        delta.ships_changed.insert(id, ship_diff(old, ship));
+                    }
+                    Some(_) => {}
+                }
+            }
+            delta.ships_removed = removed_ids(&self.ships, &newer.ships);
+
+            delta
         }
-    }
 
-    impl From<&LargeGrid> for ShipState {
-        fn from(ship: &LargeGrid) -> Self {
-            Self {
-                name: ship.grid.name.clone(),
-                position: ship.grid.physical.placed.position.clone(),
-                blocks: ship
-                    .large_blocks
-                    .iter()
-                    .map(|b| BlockState {
-                        id: b.id,
-                        name: b.name.clone(),
-                        block_type: b.block_type.clone(),
-                        position: b.block.rel_object.position.clone(),
-                        integrity: b.block.integrity,
-                    })
-                    .collect(),
-                version: ship.grid.version,
+        /// Applies a delta produced by `diff` (client side)
+        pub fn apply(&mut self, delta: &WorldDelta) {
+            for id in &delta.players_removed {
+                self.players.remove(id);
+            }
+            for (&id, player) in &delta.players_upserted {
+                self.players.insert(id, player.clone());
+            }
+
+            for id in &delta.ships_removed {
+                self.ships.remove(id);
+            }
+            for (&id, ship) in &delta.ships_added {
+                self.ships.insert(id, ship.clone());
+            }
+            for (id, sd) in &delta.ships_changed {
+                let Some(ship) = self.ships.get_mut(id) else {
+                    continue;
+                };
+                if let Some(ref name) = sd.name {
+                    ship.name = name.clone();
+                }
+                if let Some(ref position) = sd.position {
+                    ship.position = *position;
+                }
+                if let Some(ref orientation) = sd.orientation {
+                    ship.orientation = *orientation;
+                }
+                if let Some(is_large) = sd.is_large {
+                    ship.is_large = is_large;
+                }
+                ship.blocks.retain(|b| !sd.blocks_removed.contains(&b.id));
+                for block in &sd.blocks_upserted {
+                    match ship.blocks.iter_mut().find(|b| b.id == block.id) {
+                        Some(b) => *b = block.clone(),
+                        None => ship.blocks.push(block.clone()),
+                    }
+                }
+                ship.version = sd.version;
             }
         }
     }
 
-    pub fn world_to_snapshot(world: &GameWorld) -> WorldSnapshot {
-        WorldSnapshot {
-            players: world
-                .players
-                .iter()
-                .map(|(&id, player)| (id, PlayerState::from(player)))
-                .collect(),
-            ships: world
-                .ships
+    fn removed_ids<T>(old: &HashMap<u32, T>, new: &HashMap<u32, T>) -> Vec<u32> {
+        let mut ids: Vec<u32> = old
+            .keys()
+            .filter(|id| !new.contains_key(id))
+            .copied()
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    fn ship_diff(old: &ShipState, new: &ShipState) -> ShipDelta {
+        let old_blocks: HashMap<u32, &BlockState> = old.blocks.iter().map(|b| (b.id, b)).collect();
+        let new_ids: std::collections::HashSet<u32> = new.blocks.iter().map(|b| b.id).collect();
+
+        let mut blocks_removed: Vec<u32> = old_blocks
+            .keys()
+            .filter(|id| !new_ids.contains(id))
+            .copied()
+            .collect();
+        blocks_removed.sort_unstable();
+
+        ShipDelta {
+            name: (old.name != new.name).then(|| new.name.clone()),
+            position: (old.position != new.position).then_some(new.position),
+            orientation: (old.orientation != new.orientation).then_some(new.orientation),
+            is_large: (old.is_large != new.is_large).then_some(new.is_large),
+            blocks_upserted: new
+                .blocks
                 .iter()
-                .map(|(&id, ship)| (id, ShipState::from(ship)))
+                .filter(|b| old_blocks.get(&b.id) != Some(b))
+                .cloned()
                 .collect(),
+            blocks_removed,
+            version: new.version,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(id: u32, x: i32) -> BlockState {
+        BlockState {
+            id,
+            name: format!("Block {}", id),
+            block_type: "armor".to_string(),
+            position: RelPosition::new(x, 0, 0),
+            orientation: RelOrientation::default(),
+            integrity: 100.0,
+            faction_id: None,
         }
     }
+
+    fn ship(name: &str, blocks: Vec<BlockState>) -> ShipState {
+        ShipState {
+            name: name.to_string(),
+            position: Position::new(1.0, 2.0, 3.0),
+            orientation: Orientation::default(),
+            blocks,
+            version: 1,
+            is_large: true,
+        }
+    }
+
+    fn player(name: &str, x: f32) -> PlayerState {
+        PlayerState {
+            name: name.to_string(),
+            position: Position::new(x, 0.0, 0.0),
+            health: 100.0,
+            last_input_seq: 0,
+        }
+    }
+
+    fn world() -> WorldSnapshot {
+        let mut w = WorldSnapshot::default();
+        w.players.insert(1, player("alice", 0.0));
+        w.players.insert(2, player("bob", 5.0));
+        w.ships
+            .insert(10, ship("Miner", vec![block(1, 0), block(2, 1)]));
+        w.ships.insert(11, ship("Hauler", vec![block(3, 0)]));
+        w
+    }
+
+    /// `old.apply(old.diff(new))` must give back `new`
+    fn assert_round_trip(old: &WorldSnapshot, new: &WorldSnapshot) -> WorldDelta {
+        let delta = old.diff(new);
+        let mut applied = old.clone();
+        applied.apply(&delta);
+        assert_eq!(&applied, new);
+        delta
+    }
+
+    #[test]
+    fn identical_snapshots_give_empty_delta() {
+        let w = world();
+        assert!(assert_round_trip(&w, &w).is_empty());
+    }
+
+    #[test]
+    fn round_trip_add() {
+        let old = world();
+        let mut new = old.clone();
+        new.players.insert(3, player("carol", 9.0));
+        new.ships.insert(12, ship("Scout", vec![block(4, 0)]));
+
+        let delta = assert_round_trip(&old, &new);
+        assert_eq!(delta.players_upserted.len(), 1);
+        assert!(delta.ships_added.contains_key(&12));
+        assert!(delta.ships_changed.is_empty());
+    }
+
+    #[test]
+    fn round_trip_remove() {
+        let old = world();
+        let mut new = old.clone();
+        new.players.remove(&2);
+        new.ships.remove(&11);
+
+        let delta = assert_round_trip(&old, &new);
+        assert_eq!(delta.players_removed, vec![2]);
+        assert_eq!(delta.ships_removed, vec![11]);
+    }
+
+    #[test]
+    fn round_trip_modify() {
+        let old = world();
+        let mut new = old.clone();
+        new.players.get_mut(&1).unwrap().position = Position::new(1.0, 1.0, 1.0);
+        let miner = new.ships.get_mut(&10).unwrap();
+        miner.name = "Miner II".to_string();
+        miner.is_large = false;
+        miner.position = Position::new(4.0, 5.0, 6.0);
+        miner.orientation = Orientation::new(0.0, 1.5, 0.0);
+        miner.blocks.remove(0);
+        miner.blocks[0].integrity = 50.0;
+        miner.blocks.push(block(5, 2));
+        miner.version = 2;
+
+        let delta = assert_round_trip(&old, &new);
+        let sd = &delta.ships_changed[&10];
+        assert_eq!(sd.name.as_deref(), Some("Miner II"));
+        assert_eq!(sd.is_large, Some(false));
+        assert_eq!(sd.orientation, Some(Orientation::new(0.0, 1.5, 0.0)));
+        assert_eq!(sd.blocks_removed, vec![1]);
+        assert_eq!(sd.blocks_upserted.len(), 2);
+        assert!(!delta.ships_changed.contains_key(&11));
+    }
+
+    #[test]
+    fn unchanged_ship_fields_are_not_sent() {
+        let old = world();
+        let mut new = old.clone();
+        new.ships.get_mut(&11).unwrap().version = 2;
+
+        let sd = &assert_round_trip(&old, &new).ships_changed[&11];
+        assert_eq!(
+            (&sd.name, &sd.position, &sd.is_large),
+            (&None, &None, &None)
+        );
+        assert_eq!(sd.orientation, None);
+        assert!(sd.blocks_upserted.is_empty() && sd.blocks_removed.is_empty());
+    }
+
+    /// Fails the test instead of hanging if a message never arrives
+    async fn recv<T>(rx: &mut tokio::sync::mpsc::UnboundedReceiver<T>) -> T {
+        tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv())
+            .await
+            .expect("timed out waiting for a message")
+            .expect("channel closed")
+    }
+
+    #[tokio::test]
+    async fn in_memory_connect_round_trip() {
+        let mut server = connection::GameServer::in_memory();
+        let client = server.connect_in_memory();
+
+        client
+            .send_message(Message::Connect {
+                player_name: "alice".into(),
+            })
+            .unwrap();
+        let (client_id, message) = recv(&mut server.message_rx).await;
+        assert!(matches!(message, Message::Connect { ref player_name } if player_name == "alice"));
+
+        let mut world = world();
+        world.players.insert(client_id, player("alice", 0.0));
+        server
+            .send_to_client(
+                client_id,
+                Message::Welcome {
+                    player_id: client_id,
+                    world_state: world.clone(),
+                },
+            )
+            .unwrap();
+        let mut client_rx = client.message_rx;
+        match recv(&mut client_rx).await {
+            Message::Welcome {
+                player_id,
+                world_state,
+            } => {
+                assert_eq!(player_id, client_id);
+                assert_eq!(world_state, world);
+            }
+            other => panic!("expected Welcome, got {:?}", other),
+        }
+
+        // Dropping the client's sender ends the connection like a closed socket
+        drop(client.ws_tx);
+        let (id, message) = recv(&mut server.message_rx).await;
+        assert_eq!(id, client_id);
+        assert!(matches!(message, Message::Disconnect));
+    }
+
+    #[tokio::test]
+    async fn in_memory_clients_get_distinct_ids() {
+        let mut server = connection::GameServer::in_memory();
+        let (a, b) = (server.connect_in_memory(), server.connect_in_memory());
+        a.send_message(Message::Disconnect).unwrap();
+        b.send_message(Message::Disconnect).unwrap();
+        let (first, _) = recv(&mut server.message_rx).await;
+        let (second, _) = recv(&mut server.message_rx).await;
+        assert_ne!(first, second);
+        assert_eq!(server.connections.len(), 2);
+    }
+
+    #[test]
+    fn snapshot_for_filters_by_interest_radius() {
+        let mut server = connection::GameServer::in_memory();
+        server.interest_radius = 100.0;
+        server.set_client_position(1, Position::new(0.0, 0.0, 0.0));
+
+        let mut w = WorldSnapshot::default();
+        w.players.insert(1, player("alice", 0.0));
+        w.players.insert(2, player("bob", 50.0));
+        w.players.insert(3, player("carol", 500.0));
+        let mut near = ship("Near", Vec::new());
+        near.position = Position::new(0.0, 60.0, 80.0); // exactly 100 away
+        let mut far = ship("Far", Vec::new());
+        far.position = Position::new(0.0, 0.0, 100.5);
+        w.ships.insert(10, near);
+        w.ships.insert(11, far);
+
+        let filtered = server.snapshot_for(1, &w);
+        let mut players: Vec<_> = filtered.players.keys().copied().collect();
+        players.sort_unstable();
+        assert_eq!(players, vec![1, 2]);
+        assert!(filtered.ships.contains_key(&10));
+        assert!(!filtered.ships.contains_key(&11));
+
+        // A client always sees itself, however far it is from its last position
+        server.set_client_position(3, Position::new(-1000.0, 0.0, 0.0));
+        let filtered = server.snapshot_for(3, &w);
+        assert_eq!(filtered.players.keys().collect::<Vec<_>>(), vec![&3]);
+        assert!(filtered.ships.is_empty());
+    }
+
+    #[test]
+    fn encode_decode_round_trip() {
+        let message = Message::WorldSnapshot { snapshot: world() };
+        let data = encode(&message);
+        assert_eq!(data[0], PROTOCOL_VERSION);
+        match decode(&data).unwrap() {
+            Message::WorldSnapshot { snapshot } => assert_eq!(snapshot, world()),
+            other => panic!("expected WorldSnapshot, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn decode_rejects_other_protocol_versions() {
+        let mut data = encode(&Message::Disconnect);
+        data[0] = PROTOCOL_VERSION.wrapping_add(1);
+        let err = decode(&data).unwrap_err().to_string();
+        assert!(err.contains("unsupported protocol version"), "{}", err);
+    }
+
+    #[test]
+    fn decode_rejects_oversized_messages() {
+        // The name alone fills the limit, its length prefix tips it over
+        let data = encode(&Message::Connect {
+            player_name: "a".repeat(MAX_MESSAGE_SIZE as usize),
+        });
+        let err = decode(&data).unwrap_err().to_string();
+        assert!(err.contains("exceeds"), "{}", err);
+
+        // A length prefix larger than the data is refused without allocating it
+        let mut data = vec![PROTOCOL_VERSION];
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&u64::MAX.to_le_bytes());
+        assert!(decode(&data).is_err());
+    }
+
+    #[test]
+    fn decode_rejects_malformed_payloads() {
+        assert!(decode(&[]).is_err());
+        let data = encode(&Message::Connect {
+            player_name: "alice".into(),
+        });
+        assert!(decode(&data[..data.len() - 1]).is_err());
+        let mut trailing = data.clone();
+        trailing.push(0);
+        assert!(decode(&trailing).is_err());
+    }
 }
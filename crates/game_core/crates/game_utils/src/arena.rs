@@ -115,7 +115,7 @@ where
     }
 
     /// Lecture groupée sans allouer
-    pub fn get_many<'a>(&'a self, ids: &'a [Id]) -> impl Iterator<Item = (Id, &T)> + 'a {
+    pub fn get_many<'a>(&'a self, ids: &'a [Id]) -> impl Iterator<Item = (Id, &'a T)> + 'a {
         ids.iter().copied().filter_map(move |id| {
             self.get_ref(id).map(|r| (id, r))
         })
@@ -142,6 +142,15 @@ where
     }
 }
 
+impl<T, Id> Default for Arena<T, Id>
+where
+    Id: Eq + Hash + Copy,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Compat: insert/get pour arènes locales portant l’Id dans T.
 impl<T, Id> Arena<T, Id>
 where
@@ -3,11 +3,17 @@
 //! - qcap = 4096
 //! - Macros: job!(label, expr) → Receiver<T>, job_do!(label, expr) → fire-and-forget
 //! - Reports: get_job_report(label), get_all_job_reports(), reset_*()
+//!   (durée d'exécution + attente en file entre la soumission et le démarrage),
+//!   format_job_reports() pour un tableau lisible (logs, overlay de debug)
+//! - Un job qui panique est isolé (catch_unwind) et compté dans `panics` ;
+//!   les jobs avec résultat ferment alors leur Receiver sans valeur.
+//!   Suppose `panic = "unwind"` (défaut) : un profil en `abort` tue le processus.
 
 use crossbeam_channel::{bounded, Receiver, Sender};
 use dashmap::DashMap;
 use once_cell::sync::Lazy;
 use rayon::ThreadPoolBuilder;
+use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::thread::{self, JoinHandle};
 use std::time::{Duration, Instant};
 
@@ -21,6 +27,9 @@ pub struct JobReport {
     pub avg_ms: f64,
     pub max_ms: f64,
     pub total_ms: f64,
+    pub avg_wait_ms: f64,
+    pub max_wait_ms: f64,
+    pub panics: u64,
 }
 
 #[derive(Default, Debug)]
@@ -28,21 +37,30 @@ struct RawStats {
     runs: u64,
     total_ns: u128,
     max_ns: u128,
+    wait_total_ns: u128,
+    wait_max_ns: u128,
+    panics: u64,
 }
 
-static STATS: Lazy<DashMap<String, RawStats>> = Lazy::new(|| DashMap::new());
+static STATS: Lazy<DashMap<String, RawStats>> = Lazy::new(DashMap::new);
 
 #[inline]
-fn record_duration(label: &str, dur: Duration) {
+fn record_duration(label: &str, wait: Duration, dur: Duration, panicked: bool) {
     let ns = dur.as_nanos();
-    let mut e = STATS
-        .entry(label.to_string())
-        .or_insert_with(RawStats::default);
+    let wait_ns = wait.as_nanos();
+    let mut e = STATS.entry(label.to_string()).or_default();
     e.runs += 1;
     e.total_ns += ns;
     if ns > e.max_ns {
         e.max_ns = ns;
     }
+    e.wait_total_ns += wait_ns;
+    if wait_ns > e.wait_max_ns {
+        e.wait_max_ns = wait_ns;
+    }
+    if panicked {
+        e.panics += 1;
+    }
 }
 
 #[inline]
@@ -54,21 +72,61 @@ fn to_report(label: &str, s: &RawStats) -> JobReport {
         0.0
     };
     let max_ms = s.max_ns as f64 / 1e6;
+    let avg_wait_ms = if s.runs > 0 {
+        s.wait_total_ns as f64 / 1e6 / s.runs as f64
+    } else {
+        0.0
+    };
     JobReport {
         label: label.to_string(),
         runs: s.runs,
         avg_ms,
         max_ms,
         total_ms,
+        avg_wait_ms,
+        max_wait_ms: s.wait_max_ns as f64 / 1e6,
+        panics: s.panics,
     }
 }
 
 pub fn get_job_report(label: &str) -> Option<JobReport> {
-    STATS.get(label).map(|g| to_report(label, &*g))
+    STATS.get(label).map(|g| to_report(label, &g))
 }
 
 pub fn get_all_job_reports() -> Vec<JobReport> {
-    STATS.iter().map(|kv| to_report(kv.key(), &*kv)).collect()
+    STATS.iter().map(|kv| to_report(kv.key(), &kv)).collect()
+}
+
+impl std::fmt::Display for JobReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:<24} {:>8} {:>9.3} {:>9.3} {:>9.3} {:>9.3} {:>7}",
+            self.label,
+            self.runs,
+            self.avg_ms,
+            self.max_ms,
+            self.avg_wait_ms,
+            self.max_wait_ms,
+            self.panics
+        )
+    }
+}
+
+/// Tableau de tous les labels, le plus coûteux (temps total) en premier.
+/// Une attente qui dépasse l'exécution signale une file engorgée, pas un job lent.
+pub fn format_job_reports() -> String {
+    let mut reports = get_all_job_reports();
+    reports.sort_by(|a, b| b.total_ms.total_cmp(&a.total_ms));
+    let mut out = format!(
+        "{:<24} {:>8} {:>9} {:>9} {:>9} {:>9} {:>7}\n",
+        "label", "runs", "avg ms", "max ms", "wait avg", "wait max", "panics"
+    );
+    for r in reports {
+        out.push_str(&r.to_string());
+        out.push('\n');
+    }
+    out
 }
 
 pub fn reset_job_report(label: &str) {
@@ -101,14 +159,16 @@ impl Threader {
                 .build_global()
                 .expect("rayon global pool");
         });
-        let _ = *POOL_INIT;
+        Lazy::force(&POOL_INIT);
 
         let (tx, rx) = bounded::<Job>(queue_cap);
 
         let dispatcher = thread::spawn(move || {
             while let Ok(job) = rx.recv() {
-                // FIFO pour limiter la famine en burst
-                rayon::spawn_fifo(job);
+                // FIFO pour limiter la famine en burst ; une panique ne doit pas tuer le pool
+                rayon::spawn_fifo(move || {
+                    let _ = catch_unwind(AssertUnwindSafe(job));
+                });
             }
         });
 
@@ -145,10 +205,11 @@ impl Threader {
     where
         F: FnOnce() + Send + 'static,
     {
+        let queued = Instant::now();
         self.submit(move || {
             let t0 = Instant::now();
-            f();
-            record_duration(label, t0.elapsed());
+            let res = catch_unwind(AssertUnwindSafe(f));
+            record_duration(label, t0 - queued, t0.elapsed(), res.is_err());
         });
     }
 
@@ -159,11 +220,14 @@ impl Threader {
         T: Send + 'static,
     {
         let (rtx, rrx) = bounded(1);
+        let queued = Instant::now();
         self.submit(move || {
             let t0 = Instant::now();
-            let out = f();
-            record_duration(label, t0.elapsed());
-            let _ = rtx.send(out);
+            let res = catch_unwind(AssertUnwindSafe(f));
+            record_duration(label, t0 - queued, t0.elapsed(), res.is_err());
+            if let Ok(out) = res {
+                let _ = rtx.send(out);
+            }
         });
         rrx
     }
@@ -173,7 +237,7 @@ impl Threader {
 static GLOBAL_THREADER: Lazy<Threader> = Lazy::new(|| Threader::new(4096));
 
 pub fn global() -> &'static Threader {
-    &*GLOBAL_THREADER
+    &GLOBAL_THREADER
 }
 
 // ---------- Macros ergonomiques ----------
@@ -191,3 +255,73 @@ macro_rules! job_do {
         $crate::threading::global().submit_profiled_do($label, move || $e)
     }};
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Labels propres à chaque test : les stats sont globales et les tests parallèles
+
+    #[test]
+    fn panicking_job_is_isolated() {
+        let failed = global().submit_profiled_result("test_panic_result", || -> u32 {
+            panic!("job en échec volontaire")
+        });
+        // Receiver fermé sans valeur
+        assert!(failed.recv().is_err());
+
+        let report = get_job_report("test_panic_result").unwrap();
+        assert_eq!(report.runs, 1);
+        assert_eq!(report.panics, 1);
+
+        // Le pool continue de servir les autres jobs
+        let results: Vec<Receiver<u32>> =
+            (0..16).map(|i| job!("test_panic_after", i * 2)).collect();
+        let sum: u32 = results.into_iter().map(|rx| rx.recv().unwrap()).sum();
+        assert_eq!(sum, (0..16).map(|i| i * 2).sum());
+
+        let report = get_job_report("test_panic_after").unwrap();
+        assert_eq!(report.runs, 16);
+        assert_eq!(report.panics, 0);
+    }
+
+    #[test]
+    fn report_shows_wait_times() {
+        let report = JobReport {
+            label: "test_format".into(),
+            runs: 4,
+            avg_ms: 1.5,
+            max_ms: 3.0,
+            total_ms: 6.0,
+            avg_wait_ms: 12.25,
+            max_wait_ms: 40.0,
+            panics: 0,
+        };
+        let line = report.to_string();
+        assert!(line.starts_with("test_format"));
+        assert!(line.contains("12.250") && line.contains("40.000"));
+
+        let rx = job!("test_format_table", 1);
+        rx.recv().unwrap();
+        let table = format_job_reports();
+        assert!(table.lines().next().unwrap().contains("wait avg"));
+        assert!(table.lines().any(|l| l.starts_with("test_format_table")));
+    }
+
+    #[test]
+    fn panicking_fire_and_forget_job_is_counted() {
+        job_do!("test_panic_do", panic!("job en échec volontaire"));
+        // Les jobs non profilés survivent aussi à une panique voisine
+        global().submit(|| panic!("job en échec volontaire"));
+        let ok = global().submit_result(|| 7);
+        assert_eq!(ok.recv().unwrap(), 7);
+
+        // job_do ne rend rien : attendre que la stat soit enregistrée
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while get_job_report("test_panic_do").is_none() {
+            assert!(Instant::now() < deadline, "job jamais exécuté");
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(get_job_report("test_panic_do").unwrap().panics, 1);
+    }
+}
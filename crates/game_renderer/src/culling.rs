@@ -0,0 +1,192 @@
+use glam::{Mat4, Vec3, Vec4};
+
+/// Axis-aligned bounding box in world space
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self {
+            min: min.min(max),
+            max: min.max(max),
+        }
+    }
+
+    pub fn from_center_half_extents(center: Vec3, half_extents: Vec3) -> Self {
+        Self::new(center - half_extents, center + half_extents)
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+}
+
+/// View frustum as six inward-facing planes (normal in xyz, distance in w),
+/// ordered left, right, bottom, top, near, far
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+    pub planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes from a view-projection matrix (Gribb & Hartmann).
+    /// Expects wgpu clip space, i.e. depth in 0..1 as produced by `perspective_rh`.
+    pub fn from_view_proj(view_proj: Mat4) -> Self {
+        let (r0, r1, r2, r3) = (
+            view_proj.row(0),
+            view_proj.row(1),
+            view_proj.row(2),
+            view_proj.row(3),
+        );
+        let planes = [r3 + r0, r3 - r0, r3 + r1, r3 - r1, r2, r3 - r2].map(|plane| {
+            // Normalized so that `distance` returns world units
+            let len = plane.truncate().length();
+            if len > 0.0 {
+                plane / len
+            } else {
+                plane
+            }
+        });
+        Self { planes }
+    }
+
+    /// Same frustum with every plane pushed `margin` world units outwards, so
+    /// objects just past an edge stay accepted while the camera turns slowly
+    pub fn with_margin(mut self, margin: f32) -> Self {
+        for plane in &mut self.planes {
+            plane.w += margin;
+        }
+        self
+    }
+
+    /// Signed distance from `plane` to `point`, positive on the inner side
+    fn distance(plane: Vec4, point: Vec3) -> f32 {
+        plane.truncate().dot(point) + plane.w
+    }
+
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        self.planes
+            .iter()
+            .all(|&plane| Self::distance(plane, point) >= 0.0)
+    }
+
+    pub fn intersects_sphere(&self, center: Vec3, radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|&plane| Self::distance(plane, center) >= -radius)
+    }
+
+    /// Conservative test: may accept a box near a frustum corner that lies just
+    /// outside, never rejects a visible one
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|&plane| {
+            // Corner furthest along the plane normal
+            let normal = plane.truncate();
+            let corner = Vec3::select(normal.cmpge(Vec3::ZERO), aabb.max, aabb.min);
+            Self::distance(plane, corner) >= 0.0
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NEAR: f32 = 0.1;
+    const FAR: f32 = 100.0;
+
+    fn frustum(eye: Vec3, target: Vec3) -> Frustum {
+        let proj = Mat4::perspective_rh(90f32.to_radians(), 16.0 / 9.0, NEAR, FAR);
+        let view = Mat4::look_at_rh(eye, target, Vec3::Y);
+        Frustum::from_view_proj(proj * view)
+    }
+
+    /// Camera basis (forward, right, up) matching `look_at_rh` with a Y up vector
+    fn basis(eye: Vec3, target: Vec3) -> (Vec3, Vec3, Vec3) {
+        let forward = (target - eye).normalize();
+        let right = forward.cross(Vec3::Y).normalize();
+        (forward, right, right.cross(forward))
+    }
+
+    /// Checks a frustum built from `eye`/`target` against points and boxes laid
+    /// out in the camera's own frame, so the same cases cover any orientation
+    fn check_camera(eye: Vec3, target: Vec3) {
+        let f = frustum(eye, target);
+        let (forward, right, up) = basis(eye, target);
+
+        for plane in f.planes {
+            assert!((plane.truncate().length() - 1.0).abs() < 1e-4);
+        }
+
+        let inside = eye + forward * 10.0;
+        assert!(f.contains_point(inside));
+        assert!(f.intersects_sphere(inside, 0.5));
+
+        // One point far beyond each plane, in left, right, bottom, top, near, far order
+        let outside = [
+            inside - right * 1000.0,
+            inside + right * 1000.0,
+            inside - up * 1000.0,
+            inside + up * 1000.0,
+            eye - forward * 5.0,
+            eye + forward * (FAR * 2.0),
+        ];
+        for (i, point) in outside.into_iter().enumerate() {
+            assert!(!f.contains_point(point), "plane {i}");
+            assert!(Frustum::distance(f.planes[i], point) < 0.0, "plane {i}");
+            assert!(!f.intersects_sphere(point, 1.0), "plane {i}");
+            let aabb = Aabb::from_center_half_extents(point, Vec3::splat(1.0));
+            assert!(!f.intersects_aabb(&aabb), "plane {i}");
+        }
+
+        // Box reaching from inside the frustum to well past the left plane
+        let straddling = Aabb::new(inside - right * 100.0, inside + Vec3::splat(0.5));
+        assert!(f.intersects_aabb(&straddling));
+        // Box enclosing the whole frustum
+        let enclosing = Aabb::from_center_half_extents(eye, Vec3::splat(FAR * 4.0));
+        assert!(f.intersects_aabb(&enclosing));
+    }
+
+    #[test]
+    fn origin_is_inside() {
+        let f = frustum(Vec3::new(0.0, 0.0, 5.0), Vec3::ZERO);
+        assert!(f.contains_point(Vec3::ZERO));
+        assert!(f.intersects_aabb(&Aabb::from_center_half_extents(
+            Vec3::ZERO,
+            Vec3::splat(0.5)
+        )));
+    }
+
+    #[test]
+    fn margin_pushes_planes_outwards() {
+        let eye = Vec3::new(0.0, 0.0, 5.0);
+        let f = frustum(eye, Vec3::ZERO);
+        let wide = f.with_margin(2.0);
+        let (forward, _, _) = basis(eye, Vec3::ZERO);
+        let inside = eye + forward * 10.0;
+        for i in 0..6 {
+            let gained =
+                Frustum::distance(wide.planes[i], inside) - Frustum::distance(f.planes[i], inside);
+            assert!((gained - 2.0).abs() < 1e-4, "plane {i}");
+        }
+        // One unit past the left plane: rejected as is, accepted with the margin
+        let left = f.planes[0];
+        let past = inside - left.truncate() * (Frustum::distance(left, inside) + 1.0);
+        assert!(!f.contains_point(past));
+        assert!(wide.contains_point(past));
+    }
+
+    #[test]
+    fn axis_aligned_camera() {
+        check_camera(Vec3::new(0.0, 0.0, 5.0), Vec3::ZERO);
+    }
+
+    #[test]
+    fn rotated_camera() {
+        check_camera(Vec3::new(3.0, 2.0, -4.0), Vec3::new(-6.0, 5.0, 1.0));
+        check_camera(Vec3::new(-10.0, 0.0, 0.0), Vec3::new(0.0, -3.0, 0.0));
+    }
+}
@@ -3,16 +3,45 @@ use glam::{Mat4, Vec3};
 use wgpu::util::DeviceExt;
 use winit::{event::ElementState, keyboard::KeyCode, window::Window};
 
+mod culling;
 mod overlay;
 mod scene_cache;
+pub use culling::{Aabb, Frustum};
 use overlay::OverlayRenderer;
 use scene_cache::SceneCache;
 
+/// Number of frames kept in the frame-time graph
+const FRAME_HISTORY_LEN: usize = 120;
+
 pub struct OverlayData {
     pub fps: f32,
     pub player_position: Option<Vec3>,
+    /// Last frame times in milliseconds, oldest first
+    pub frame_times: std::collections::VecDeque<f32>,
+    pub show_frame_graph: bool,
+    /// FPS / position / target panel
+    pub show_stats: bool,
+    /// Center of the block under the crosshair
+    pub target_block: Option<Vec3>,
+    /// Screen-space edges of the selected block outline
+    pub selection_edges: Vec<[(f32, f32); 2]>,
+    /// World labels projected for this frame
+    pub labels: Vec<ScreenLabel>,
+}
+
+/// Text anchored at a pixel position (centered), faded by `alpha`
+pub struct ScreenLabel {
+    pub x: f32,
+    pub y: f32,
+    pub text: String,
+    pub alpha: f32,
 }
 
+/// Labels start fading at this distance from the camera...
+const LABEL_FADE_START: f32 = 40.0;
+/// ...and are hidden beyond this one
+const LABEL_MAX_DISTANCE: f32 = 80.0;
+
 // Vertex structure for 3D cubes
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
@@ -54,14 +83,50 @@ impl Vertex {
 struct CameraUniform {
     view_proj: [[f32; 4]; 4],
     view_pos: [f32; 3],
-    _padding: f32,
+    /// Multiplies the final fragment color (1.0 = unchanged)
+    brightness: f32,
+    /// Non-zero skips lighting: blocks show their unshaded color
+    fullbright: u32,
+    _padding: [u32; 3],
 }
 
-// Model uniform buffer data
+// Per-instance data: model matrix columns then tint
 #[repr(C)]
 #[derive(Debug, Copy, Clone, Pod, Zeroable)]
-struct ModelUniform {
-    matrix: [[f32; 4]; 4],
+struct InstanceRaw {
+    model: [[f32; 4]; 4],
+    color: [f32; 4],
+}
+
+impl InstanceRaw {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+        3 => Float32x4,
+        4 => Float32x4,
+        5 => Float32x4,
+        6 => Float32x4,
+        7 => Float32x4,
+    ];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<InstanceRaw>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+
+    fn translation(&self) -> Vec3 {
+        Vec3::new(self.model[3][0], self.model[3][1], self.model[3][2])
+    }
+}
+
+/// Where a draw takes its instances from
+enum InstanceSource {
+    /// The whole cached buffer of a texture group
+    Cached(String),
+    /// A range of the per-frame instance buffer, with its texture
+    /// (`None` for entities, drawn with the default texture)
+    Frame(Option<String>, std::ops::Range<u32>),
 }
 
 // Create cube vertices (Space Engineers block)
@@ -203,6 +268,264 @@ const CUBE_INDICES: &[u16] = &[
     20, 21, 22, 22, 23, 20, // Left
 ];
 
+/// Half size of the cube mesh, used for block bounding boxes when culling
+const BLOCK_HALF_EXTENT: f32 = 0.5;
+
+/// World units the culling frustum is widened by, so blocks at the screen edge
+/// don't toggle between culled and visible while the camera pans
+const CULL_MARGIN: f32 = 1.0;
+
+/// Frustum used to cull blocks for the given camera (see `CULL_MARGIN`)
+fn culling_frustum(view_proj: Mat4) -> Frustum {
+    Frustum::from_view_proj(view_proj).with_margin(CULL_MARGIN)
+}
+
+/// Default instance upload budget: about 1.25 MiB of `InstanceRaw` per frame
+const DEFAULT_MAX_INSTANCE_UPLOADS: usize = 16 * 1024;
+
+/// Groups blocks by texture, each group sorted by id so its key is comparable
+/// between frames
+fn group_by_texture(
+    blocks: &[BlockInstance],
+) -> std::collections::BTreeMap<&str, Vec<&BlockInstance>> {
+    let mut groups: std::collections::BTreeMap<&str, Vec<&BlockInstance>> =
+        std::collections::BTreeMap::new();
+    for block in blocks {
+        groups.entry(&block.texture_path).or_default().push(block);
+    }
+    for blocks in groups.values_mut() {
+        blocks.sort_by_key(|block| block.id);
+    }
+    groups
+}
+
+/// Whether a group of `group_instances` still fits once `uploaded` instances were
+/// rebuilt this frame. The first group always fits, so one larger than the whole
+/// budget is still uploaded, alone.
+fn fits_upload_budget(uploaded: usize, group_instances: usize, budget: usize) -> bool {
+    uploaded == 0 || uploaded + group_instances <= budget
+}
+
+/// Instances to draw this frame, in order: `entities` (always rewritten), then each
+/// texture group, whole from its cached buffer or, when partly outside `frustum`,
+/// as its visible blocks copied to the frame buffer.
+/// Returns the frame instances, the draws and the number of culled blocks.
+fn plan_draws<'a>(
+    entities: Vec<InstanceRaw>,
+    groups: impl Iterator<Item = (&'a str, &'a [InstanceRaw])>,
+    frustum: Option<&Frustum>,
+) -> (Vec<InstanceRaw>, Vec<InstanceSource>, usize) {
+    let mut frame_instances = entities;
+    let mut draws = Vec::new();
+    if !frame_instances.is_empty() {
+        draws.push(InstanceSource::Frame(None, 0..frame_instances.len() as u32));
+    }
+
+    let mut culled = 0;
+    for (texture_path, instances) in groups {
+        let Some(frustum) = frustum else {
+            draws.push(InstanceSource::Cached(texture_path.to_string()));
+            continue;
+        };
+
+        let visible: Vec<&InstanceRaw> = instances
+            .iter()
+            .filter(|instance| {
+                frustum.intersects_aabb(&Aabb::from_center_half_extents(
+                    instance.translation(),
+                    Vec3::splat(BLOCK_HALF_EXTENT),
+                ))
+            })
+            .collect();
+        culled += instances.len() - visible.len();
+        if visible.len() == instances.len() {
+            draws.push(InstanceSource::Cached(texture_path.to_string()));
+        } else if !visible.is_empty() {
+            let start = frame_instances.len() as u32;
+            frame_instances.extend(visible.into_iter().copied());
+            draws.push(InstanceSource::Frame(
+                Some(texture_path.to_string()),
+                start..frame_instances.len() as u32,
+            ));
+        }
+    }
+    (frame_instances, draws, culled)
+}
+
+/// Size of each level of a full mip chain, halving (rounded down, at least 1)
+/// down to 1x1
+fn mip_sizes(width: u32, height: u32) -> Vec<(u32, u32)> {
+    let count = width.max(height).max(1).ilog2() + 1;
+    std::iter::successors(Some((width.max(1), height.max(1))), |&(w, h)| {
+        Some(((w / 2).max(1), (h / 2).max(1)))
+    })
+    .take(count as usize)
+    .collect()
+}
+
+/// Loads each path not cached yet. Failures are cached as `None` (default
+/// texture), so a path is loaded, or warned about, at most once
+fn fill_texture_cache<T, E: std::fmt::Display>(
+    cache: &mut ahash::AHashMap<String, Option<T>>,
+    paths: impl IntoIterator<Item = String>,
+    mut load: impl FnMut(&str) -> Result<T, E>,
+) {
+    for path in paths {
+        if cache.contains_key(&path) {
+            continue;
+        }
+        let texture = match load(&path) {
+            Ok(texture) => Some(texture),
+            Err(e) => {
+                tracing::warn!("Texture {} unavailable, using default: {}", path, e);
+                None
+            }
+        };
+        cache.insert(path, texture);
+    }
+}
+
+/// Decodes an image file into an sRGB texture with a full mip chain, so the
+/// trilinear sampler has levels to blend between
+fn load_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    path: &str,
+) -> Result<wgpu::TextureView, image::ImageError> {
+    let mut level = image::open(path)?.to_rgba8();
+    let (width, height) = level.dimensions();
+    let sizes = mip_sizes(width, height);
+    let mip_level_count = sizes.len() as u32;
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(path),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+
+    for (mip_level, &(w, h)) in (0..).zip(&sizes) {
+        if mip_level > 0 {
+            level = image::imageops::resize(&level, w, h, image::imageops::FilterType::Triangle);
+        }
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &level,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * level.width()),
+                rows_per_image: Some(level.height()),
+            },
+            wgpu::Extent3d {
+                width: level.width(),
+                height: level.height(),
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+    Ok(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+}
+
+/// Initial capacity of the per-frame instance buffer
+const MIN_FRAME_INSTANCES: usize = 256;
+
+fn create_instance_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Frame Instance Buffer"),
+        size: (capacity * std::mem::size_of::<InstanceRaw>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+/// Format of the scene depth buffer; the overlay pipeline declares it too
+/// since it shares the attachment
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// Depth buffer matching the surface size; recreated on every resize
+fn create_depth_view(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+) -> wgpu::TextureView {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Depth Texture"),
+        size: wgpu::Extent3d {
+            width: config.width.max(1),
+            height: config.height.max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: DEPTH_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+/// NaN falls back to the default brightness
+fn clamp_brightness(brightness: f32) -> f32 {
+    if brightness.is_nan() {
+        1.0
+    } else {
+        brightness.clamp(0.0, 4.0)
+    }
+}
+
+/// Anisotropic filtering level used until `set_anisotropy` is called
+const DEFAULT_ANISOTROPY: u16 = 8;
+
+/// Trilinear sampler (anisotropy requires linear filtering on all three filters)
+fn create_block_sampler(device: &wgpu::Device, anisotropy: u16) -> wgpu::Sampler {
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("Block Sampler"),
+        address_mode_u: wgpu::AddressMode::ClampToEdge,
+        address_mode_v: wgpu::AddressMode::ClampToEdge,
+        address_mode_w: wgpu::AddressMode::ClampToEdge,
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        mipmap_filter: wgpu::FilterMode::Linear,
+        anisotropy_clamp: anisotropy.clamp(1, 16),
+        ..Default::default()
+    })
+}
+
+fn create_texture_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    view: &wgpu::TextureView,
+    anisotropy: u16,
+) -> wgpu::BindGroup {
+    let sampler = create_block_sampler(device, anisotropy);
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&sampler),
+            },
+        ],
+        label: Some("default_texture_bind_group"),
+    })
+}
+
 pub struct Renderer {
     surface: wgpu::Surface<'static>,
     device: wgpu::Device,
@@ -211,52 +534,208 @@ pub struct Renderer {
     size: winit::dpi::PhysicalSize<u32>,
     window: std::sync::Arc<Window>,
     render_pipeline: wgpu::RenderPipeline,
+    depth_view: wgpu::TextureView,
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
     num_indices: u32,
     camera_uniform: CameraUniform,
     camera_buffer: wgpu::Buffer,
     camera_bind_group: wgpu::BindGroup,
-    model_bind_group_layout: wgpu::BindGroupLayout,
-    #[allow(dead_code)]
     texture_bind_group_layout: wgpu::BindGroupLayout,
     default_texture_bind_group: wgpu::BindGroup,
+    default_texture_view: wgpu::TextureView,
+    /// Block textures by path, loaded on first use; `None` when the file could
+    /// not be loaded (drawn with the default texture)
+    textures: ahash::AHashMap<String, Option<(wgpu::TextureView, wgpu::BindGroup)>>,
+    /// Anisotropic filtering level of the block sampler (1 = off)
+    anisotropy: u16,
     blocks_to_render: Vec<BlockInstance>,
+    entities_to_render: Vec<EntityInstance>,
+    /// Rewritten every frame: entities, then blocks of partly culled groups
+    frame_instance_buffer: wgpu::Buffer,
+    /// Capacity of `frame_instance_buffer`, in instances
+    frame_instance_capacity: usize,
     overlay_data: OverlayData,
     overlay_renderer: OverlayRenderer,
+    last_frame_instant: Option<std::time::Instant>,
+    selection: Option<Vec3>,
+    labels: Vec<(Vec3, String)>,
+    /// Window has a zero-sized client area; the surface is left unconfigured
+    minimized: bool,
     scene_cache: SceneCache,
+    /// Instances rebuilt per frame across all dirty groups (see `fits_upload_budget`)
+    max_instance_uploads_per_frame: usize,
+    /// Skip blocks whose bounding box is outside the camera frustum
+    frustum_culling: bool,
+    clear_color: wgpu::Color,
+    block_colors: ahash::AHashMap<String, [f32; 4]>,
+    faction_colors: ahash::AHashMap<u32, [f32; 4]>,
+    /// Present modes the surface supports, queried once at creation
+    present_modes: Vec<wgpu::PresentMode>,
+    adapter_info: wgpu::AdapterInfo,
+}
+
+/// GPU/backend selection for `Renderer::new_with_config`
+#[derive(Debug, Clone, Default)]
+pub struct RendererConfig {
+    /// Restrict to these backends (e.g. only Vulkan); all backends when `None`
+    pub backends: Option<wgpu::Backends>,
+    pub power_preference: wgpu::PowerPreference,
+    /// Use the first adapter whose name contains this (case-insensitive);
+    /// falls back to the normal selection if none matches
+    pub force_adapter_name: Option<String>,
+    /// Ask for a software (CPU) adapter, for headless or driverless machines
+    pub force_fallback_adapter: bool,
+    /// Initial scene brightness (see `Renderer::set_brightness`); 1.0 when `None`
+    pub brightness: Option<f32>,
+}
+
+impl RendererConfig {
+    /// Reads the standard `WGPU_BACKEND`, `WGPU_POWER_PREF` and `WGPU_ADAPTER_NAME` variables,
+    /// plus `GAME_BRIGHTNESS`
+    pub fn from_env() -> Self {
+        Self {
+            backends: wgpu::Backends::from_env(),
+            power_preference: wgpu::PowerPreference::from_env().unwrap_or_default(),
+            force_adapter_name: std::env::var("WGPU_ADAPTER_NAME").ok(),
+            force_fallback_adapter: false,
+            brightness: std::env::var("GAME_BRIGHTNESS")
+                .ok()
+                .and_then(|v| v.parse().ok()),
+        }
+    }
+}
+
+/// Why `Renderer::new_with_config` could not start
+#[derive(Debug)]
+pub enum RendererError {
+    /// The window could not be turned into a wgpu surface
+    Surface(wgpu::CreateSurfaceError),
+    /// No adapter matched the requested backends/power preference and surface
+    NoAdapter(String),
+    /// The adapter refused to create a device
+    NoDevice(String),
+    /// The adapter reports no format or present mode for this surface
+    IncompatibleSurface,
+}
+
+impl std::fmt::Display for RendererError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RendererError::Surface(e) => write!(f, "failed to create the window surface: {e}"),
+            RendererError::NoAdapter(e) => write!(f, "no compatible GPU found: {e}"),
+            RendererError::NoDevice(e) => write!(f, "failed to create the GPU device: {e}"),
+            RendererError::IncompatibleSurface => {
+                write!(f, "the GPU cannot present to this window")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RendererError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RendererError::Surface(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Adapters available on the given backends, e.g. to offer a GPU choice in settings
+pub fn available_adapters(backends: wgpu::Backends) -> Vec<wgpu::AdapterInfo> {
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+        backends,
+        ..Default::default()
+    });
+    instance
+        .enumerate_adapters(backends)
+        .iter()
+        .map(|a| a.get_info())
+        .collect()
+}
+
+/// Forced adapter by name if one matches (and can present to `surface`),
+/// otherwise wgpu's pick. `surface` is `None` when nothing will be presented.
+async fn select_adapter(
+    instance: &wgpu::Instance,
+    backends: wgpu::Backends,
+    config: &RendererConfig,
+    surface: Option<&wgpu::Surface<'_>>,
+) -> Result<wgpu::Adapter, RendererError> {
+    let forced_adapter = config.force_adapter_name.as_ref().and_then(|name| {
+        let name = name.to_lowercase();
+        instance.enumerate_adapters(backends).into_iter().find(|a| {
+            a.get_info().name.to_lowercase().contains(&name)
+                && surface.is_none_or(|s| a.is_surface_supported(s))
+        })
+    });
+    match forced_adapter {
+        Some(adapter) => Ok(adapter),
+        None => instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: config.power_preference,
+                compatible_surface: surface,
+                force_fallback_adapter: config.force_fallback_adapter,
+            })
+            .await
+            .map_err(|e| RendererError::NoAdapter(e.to_string())),
+    }
 }
 
 #[derive(Clone)]
 pub struct BlockInstance {
     pub id: u32,
     pub version: u64,
-    pub position: Vec3,
+    /// Model matrix of the block's cube: grid and block rotation plus world position
+    pub transform: Mat4,
+    pub block_type: String,
     pub texture_path: String,
+    /// Owner faction, tints the block with its color (see `set_faction_colors`)
+    pub faction_id: Option<u32>,
+}
+
+impl BlockInstance {
+    /// World position of the block's center
+    pub fn position(&self) -> Vec3 {
+        self.transform.w_axis.truncate()
+    }
+}
+
+/// Moving entity (player, ship marker...) drawn as a tinted cube.
+/// Unlike blocks, its model buffer is rewritten every frame.
+#[derive(Clone)]
+pub struct EntityInstance {
+    pub id: u32,
+    pub position: Vec3,
+    pub scale: Vec3,
+    pub color: [f32; 4],
 }
 
 impl Renderer {
-    pub async fn new(window: std::sync::Arc<Window>) -> Result<Self, Box<dyn std::error::Error>> {
+    pub async fn new(window: std::sync::Arc<Window>) -> Result<Self, RendererError> {
+        Self::new_with_config(window, RendererConfig::default()).await
+    }
+
+    pub async fn new_with_config(
+        window: std::sync::Arc<Window>,
+        renderer_config: RendererConfig,
+    ) -> Result<Self, RendererError> {
         let size = window.inner_size();
+        let backends = renderer_config.backends.unwrap_or(wgpu::Backends::all());
 
         // Create wgpu instance
         let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::all(),
+            backends,
             ..Default::default()
         });
 
         // Create surface
-        let surface = instance.create_surface(window.clone())?;
+        let surface = instance
+            .create_surface(window.clone())
+            .map_err(RendererError::Surface)?;
 
-        // Request adapter
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .map_err(|e| format!("Failed to find adapter: {:?}", e))?;
+        let adapter = select_adapter(&instance, backends, &renderer_config, Some(&surface)).await?;
+        let adapter_info = adapter.get_info();
 
         // Request device and queue
         let (device, queue) = adapter
@@ -269,10 +748,16 @@ impl Renderer {
                 trace: Default::default(),
             })
             .await
-            .map_err(|e| format!("Failed to create device: {:?}", e))?;
+            .map_err(|e| RendererError::NoDevice(e.to_string()))?;
 
         // Configure surface
         let surface_caps = surface.get_capabilities(&adapter);
+        if surface_caps.formats.is_empty()
+            || surface_caps.present_modes.is_empty()
+            || surface_caps.alpha_modes.is_empty()
+        {
+            return Err(RendererError::IncompatibleSurface);
+        }
         let surface_format = surface_caps
             .formats
             .iter()
@@ -292,6 +777,8 @@ impl Renderer {
         };
 
         surface.configure(&device, &config);
+        let depth_view = create_depth_view(&device, &config);
+        let present_modes = surface_caps.present_modes.clone();
 
         // Create basic shader for cube rendering
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
@@ -304,7 +791,7 @@ impl Renderer {
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 entries: &[wgpu::BindGroupLayoutEntry {
                     binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
                         has_dynamic_offset: false,
@@ -315,23 +802,7 @@ impl Renderer {
                 label: Some("camera_bind_group_layout"),
             });
 
-        // Create model bind group layout
-        let model_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }],
-                label: Some("model_bind_group_layout"),
-            });
-
-        // Create texture bind group layout (for future texture support)
+        // Create texture bind group layout
         let texture_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 entries: &[
@@ -359,11 +830,7 @@ impl Renderer {
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[
-                    &camera_bind_group_layout,
-                    &model_bind_group_layout,
-                    &texture_bind_group_layout,
-                ],
+                bind_group_layouts: &[&camera_bind_group_layout, &texture_bind_group_layout],
                 push_constant_ranges: &[],
             });
 
@@ -373,7 +840,7 @@ impl Renderer {
             vertex: wgpu::VertexState {
                 module: &shader,
                 entry_point: Some("vs_main"),
-                buffers: &[Vertex::desc()],
+                buffers: &[Vertex::desc(), InstanceRaw::desc()],
                 compilation_options: wgpu::PipelineCompilationOptions::default(),
             },
             fragment: Some(wgpu::FragmentState {
@@ -395,7 +862,13 @@ impl Renderer {
                 unclipped_depth: false,
                 conservative: false,
             },
-            depth_stencil: None,
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
             multisample: wgpu::MultisampleState {
                 count: 1,
                 mask: !0,
@@ -424,7 +897,9 @@ impl Renderer {
         let camera_uniform = CameraUniform {
             view_proj: Mat4::IDENTITY.to_cols_array_2d(),
             view_pos: [0.0, 0.0, 5.0],
-            _padding: 0.0,
+            brightness: clamp_brightness(renderer_config.brightness.unwrap_or(1.0)),
+            fullbright: 0,
+            _padding: [0; 3],
         };
 
         let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -472,38 +947,29 @@ impl Renderer {
 
         let default_texture_view =
             default_texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let default_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            ..Default::default()
-        });
-
-        let default_texture_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &texture_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&default_texture_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&default_sampler),
-                },
-            ],
-            label: Some("default_texture_bind_group"),
-        });
+        let anisotropy = DEFAULT_ANISOTROPY;
+        let default_texture_bind_group = create_texture_bind_group(
+            &device,
+            &texture_bind_group_layout,
+            &default_texture_view,
+            anisotropy,
+        );
 
         let overlay_data = OverlayData {
             fps: 0.0,
             player_position: None,
+            frame_times: std::collections::VecDeque::with_capacity(FRAME_HISTORY_LEN),
+            show_frame_graph: false,
+            show_stats: true,
+            target_block: None,
+            selection_edges: Vec::new(),
+            labels: Vec::new(),
         };
 
         let overlay_renderer = OverlayRenderer::new(&device, config.format);
         let scene_cache = SceneCache::new();
+        let frame_instance_capacity = MIN_FRAME_INSTANCES;
+        let frame_instance_buffer = create_instance_buffer(&device, frame_instance_capacity);
 
         Ok(Self {
             window,
@@ -513,22 +979,50 @@ impl Renderer {
             config,
             size,
             render_pipeline,
+            depth_view,
             vertex_buffer,
             index_buffer,
             num_indices,
             camera_uniform,
             camera_buffer,
             camera_bind_group,
-            model_bind_group_layout,
             texture_bind_group_layout,
             default_texture_bind_group,
+            default_texture_view,
+            textures: ahash::AHashMap::new(),
+            anisotropy,
             blocks_to_render: Vec::new(),
+            entities_to_render: Vec::new(),
+            frame_instance_buffer,
+            frame_instance_capacity,
             overlay_data,
             overlay_renderer,
+            last_frame_instant: None,
+            selection: None,
+            labels: Vec::new(),
+            minimized: false,
             scene_cache,
+            max_instance_uploads_per_frame: DEFAULT_MAX_INSTANCE_UPLOADS,
+            frustum_culling: true,
+            clear_color: wgpu::Color {
+                r: 0.1,
+                g: 0.1,
+                b: 0.2,
+                a: 1.0,
+            },
+            block_colors: ahash::AHashMap::new(),
+            faction_colors: ahash::AHashMap::new(),
+            present_modes,
+            adapter_info,
         })
     }
 
+    /// Adapter actually in use (compare with `RendererConfig::force_adapter_name`
+    /// to detect a fallback)
+    pub fn adapter_info(&self) -> &wgpu::AdapterInfo {
+        &self.adapter_info
+    }
+
     pub fn window(&self) -> &Window {
         &self.window
     }
@@ -538,57 +1032,242 @@ impl Renderer {
     }
 
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
-        if new_size.width > 0 && new_size.height > 0 {
+        self.minimized = new_size.width == 0 || new_size.height == 0;
+        if !self.minimized {
             self.size = new_size;
             self.config.width = new_size.width;
             self.config.height = new_size.height;
             self.surface.configure(&self.device, &self.config);
+            self.depth_view = create_depth_view(&self.device, &self.config);
         }
     }
 
-    fn update_bind_groups_cache(&mut self) {
-        // Nettoie le cache des objets obsolètes
-        let active_objects: Vec<(u32, u64)> = self
-            .blocks_to_render
+    fn update_instance_groups(&mut self) {
+        let groups = group_by_texture(&self.blocks_to_render);
+        let active: ahash::AHashSet<&str> = groups.keys().copied().collect();
+        self.scene_cache.cleanup_old_entries(&active);
+
+        // Reconstruit les groupes modifiés, dans la limite du budget d'instances par frame
+        let mut rebuilt = Vec::new();
+        let mut uploaded_instances = 0;
+        let mut pending = 0;
+        for (texture_path, blocks) in groups {
+            let key: Vec<(u32, u64)> = blocks.iter().map(|b| (b.id, b.version)).collect();
+            if !self.scene_cache.is_dirty(texture_path, &key) {
+                continue;
+            }
+            if !fits_upload_budget(
+                uploaded_instances,
+                blocks.len(),
+                self.max_instance_uploads_per_frame,
+            ) {
+                pending += 1;
+                continue;
+            }
+            uploaded_instances += blocks.len();
+
+            let instances: Vec<InstanceRaw> = blocks
+                .iter()
+                .map(|block| InstanceRaw {
+                    model: block.transform.to_cols_array_2d(),
+                    color: self.block_color(block),
+                })
+                .collect();
+            let buffer = self
+                .device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(&format!("Instance Buffer {}", texture_path)),
+                    contents: bytemuck::cast_slice(&instances),
+                    usage: wgpu::BufferUsages::VERTEX,
+                });
+            rebuilt.push((texture_path.to_string(), key, instances, buffer));
+        }
+
+        let uploaded = rebuilt.len();
+        for (texture_path, key, instances, buffer) in rebuilt {
+            self.scene_cache
+                .cache_group(texture_path, key, instances, buffer);
+        }
+        self.scene_cache
+            .record_uploads(uploaded, uploaded_instances, pending);
+    }
+
+    /// Entities and cached texture groups to draw this frame (see `plan_draws`)
+    fn collect_draws(&self) -> (Vec<InstanceRaw>, Vec<InstanceSource>, usize) {
+        let entities = self
+            .entities_to_render
             .iter()
-            .map(|block| (block.id, block.version))
+            .map(|entity| InstanceRaw {
+                model: Mat4::from_scale_rotation_translation(
+                    entity.scale,
+                    glam::Quat::IDENTITY,
+                    entity.position,
+                )
+                .to_cols_array_2d(),
+                color: entity.color,
+            })
             .collect();
-        self.scene_cache.cleanup_old_entries(&active_objects);
-
-        // Vérifie et met à jour les bind groups nécessaires
-        for block in &self.blocks_to_render {
-            if self.scene_cache.is_dirty(block.id, block.version) {
-                let model_matrix = Mat4::from_translation(block.position);
-                let model_uniform = ModelUniform {
-                    matrix: model_matrix.to_cols_array_2d(),
-                };
+        let frustum = self
+            .frustum_culling
+            .then(|| culling_frustum(Mat4::from_cols_array_2d(&self.camera_uniform.view_proj)));
+        let groups = self
+            .scene_cache
+            .groups()
+            .map(|(path, group)| (path, group.instances.as_slice()));
+        plan_draws(entities, groups, frustum.as_ref())
+    }
 
-                let block_model_buffer =
-                    self.device
-                        .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                            label: Some(&format!("Model Buffer {}", block.id)),
-                            contents: bytemuck::cast_slice(&[model_uniform]),
-                            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-                        });
-
-                let new_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
-                    layout: &self.model_bind_group_layout,
-                    entries: &[wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: block_model_buffer.as_entire_binding(),
-                    }],
-                    label: Some(&format!("model_bind_group_{}", block.id)),
-                });
+    /// Loads the texture of every block group seen for the first time.
+    /// A file that fails to load is reported once, then drawn with the default texture.
+    fn load_missing_textures(&mut self) {
+        let missing: Vec<String> = self
+            .scene_cache
+            .groups()
+            .map(|(path, _)| path)
+            .filter(|path| !self.textures.contains_key(*path))
+            .map(str::to_string)
+            .collect();
+        fill_texture_cache(&mut self.textures, missing, |path| {
+            let view = load_texture(&self.device, &self.queue, path)?;
+            let bind_group = create_texture_bind_group(
+                &self.device,
+                &self.texture_bind_group_layout,
+                &view,
+                self.anisotropy,
+            );
+            Ok::<_, image::ImageError>((view, bind_group))
+        });
+    }
 
-                self.scene_cache
-                    .cache_bind_group(block.id, block.version, new_bind_group);
-            }
+    /// Bind group of a loaded block texture, or the default white one
+    fn texture_bind_group(&self, texture_path: Option<&str>) -> &wgpu::BindGroup {
+        texture_path
+            .and_then(|path| self.textures.get(path))
+            .and_then(|texture| texture.as_ref())
+            .map_or(&self.default_texture_bind_group, |(_, bind_group)| {
+                bind_group
+            })
+    }
+
+    /// Uploads this frame's instances, growing the buffer if needed
+    fn upload_frame_instances(&mut self, instances: &[InstanceRaw]) {
+        if instances.len() > self.frame_instance_capacity {
+            self.frame_instance_capacity = instances.len().next_power_of_two();
+            self.frame_instance_buffer =
+                create_instance_buffer(&self.device, self.frame_instance_capacity);
+        }
+        if !instances.is_empty() {
+            self.queue.write_buffer(
+                &self.frame_instance_buffer,
+                0,
+                bytemuck::cast_slice(instances),
+            );
+        }
+    }
+
+    /// Limite le nombre d'instances reconstruites par frame ; les groupes qui ne
+    /// rentrent pas sont étalés sur les frames suivantes
+    pub fn set_max_instance_uploads_per_frame(&mut self, max: usize) {
+        self.max_instance_uploads_per_frame = max.max(1);
+    }
+
+    pub fn present_mode(&self) -> wgpu::PresentMode {
+        self.config.present_mode
+    }
+
+    /// Switches the present mode by reconfiguring the surface (device is kept).
+    /// Unsupported modes fall back to Fifo, which every surface supports;
+    /// returns the mode actually applied so callers can report the fallback.
+    pub fn set_present_mode(&mut self, mode: wgpu::PresentMode) -> wgpu::PresentMode {
+        let applied = if self.present_modes.contains(&mode) {
+            mode
+        } else {
+            wgpu::PresentMode::Fifo
+        };
+        self.config.present_mode = applied;
+        if !self.minimized {
+            self.surface.configure(&self.device, &self.config);
+        }
+        applied
+    }
+
+    /// Vsync maps to Fifo; without it, `low_latency` prefers Immediate (may tear)
+    /// over Mailbox
+    pub fn set_vsync(&mut self, vsync: bool, low_latency: bool) -> wgpu::PresentMode {
+        let preferred: &[wgpu::PresentMode] = match (vsync, low_latency) {
+            (true, _) => &[wgpu::PresentMode::Fifo],
+            (false, true) => &[wgpu::PresentMode::Immediate, wgpu::PresentMode::Mailbox],
+            (false, false) => &[wgpu::PresentMode::Mailbox, wgpu::PresentMode::Immediate],
+        };
+        let mode = preferred
+            .iter()
+            .copied()
+            .find(|m| self.present_modes.contains(m))
+            .unwrap_or(wgpu::PresentMode::Fifo);
+        self.set_present_mode(mode)
+    }
+
+    /// Sets the block sampler's anisotropic filtering level, clamped to 1..=16
+    /// (1 disables it). Returns the level applied.
+    pub fn set_anisotropy(&mut self, level: u16) -> u16 {
+        self.anisotropy = level.clamp(1, 16);
+        self.default_texture_bind_group = create_texture_bind_group(
+            &self.device,
+            &self.texture_bind_group_layout,
+            &self.default_texture_view,
+            self.anisotropy,
+        );
+        for (view, bind_group) in self.textures.values_mut().flatten() {
+            *bind_group = create_texture_bind_group(
+                &self.device,
+                &self.texture_bind_group_layout,
+                view,
+                self.anisotropy,
+            );
         }
+        self.anisotropy
+    }
+
+    pub fn anisotropy(&self) -> u16 {
+        self.anisotropy
+    }
+
+    /// Scales the final color of every lit fragment, clamped to 0..=4.
+    /// Returns the value applied; uploaded with the next `update_camera`.
+    pub fn set_brightness(&mut self, brightness: f32) -> f32 {
+        self.camera_uniform.brightness = clamp_brightness(brightness);
+        self.camera_uniform.brightness
+    }
+
+    pub fn brightness(&self) -> f32 {
+        self.camera_uniform.brightness
+    }
+
+    /// Switches lighting off (or back on); returns the new state
+    pub fn toggle_fullbright(&mut self) -> bool {
+        self.camera_uniform.fullbright ^= 1;
+        self.is_fullbright()
+    }
+
+    pub fn is_fullbright(&self) -> bool {
+        self.camera_uniform.fullbright != 0
+    }
+
+    pub fn is_minimized(&self) -> bool {
+        self.minimized
     }
 
     pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        // Met à jour le cache des bind groups avant le rendu
-        self.update_bind_groups_cache();
+        // Nothing to present to; acquiring a texture would error every frame
+        if self.minimized {
+            return Ok(());
+        }
+        self.record_frame_time();
+        // Met à jour les buffers d'instances avant le rendu
+        self.update_instance_groups();
+        self.load_missing_textures();
+        let (frame_instances, draws, culled) = self.collect_draws();
+        self.upload_frame_instances(&frame_instances);
         let output = self.surface.get_current_texture()?;
         let view = output
             .texture
@@ -607,17 +1286,19 @@ impl Renderer {
                     view: &view,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.1,
-                            b: 0.2,
-                            a: 1.0,
-                        }),
+                        load: wgpu::LoadOp::Clear(self.clear_color),
                         store: wgpu::StoreOp::Store,
                     },
                     depth_slice: None,
                 })],
-                depth_stencil_attachment: None,
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
                 occlusion_query_set: None,
                 timestamp_writes: None,
             });
@@ -627,25 +1308,35 @@ impl Renderer {
             render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
             render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
 
-            // Render each block instance avec cache
-            for block in &self.blocks_to_render {
-                // Utilise le bind group depuis le cache (garanti d'exister après update_bind_groups_cache)
-                if let Some(model_bind_group) =
-                    self.scene_cache.get_bind_group(block.id, block.version)
-                {
-                    render_pass.set_bind_group(1, model_bind_group, &[]);
-                    render_pass.set_bind_group(2, &self.default_texture_bind_group, &[]);
-                    render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
-                }
+            // One instanced draw per texture group (entities use the default texture)
+            for source in &draws {
+                let (texture_path, buffer, instances) = match source {
+                    InstanceSource::Cached(path) => match self.scene_cache.group(path) {
+                        Some(group) => (
+                            Some(path.as_str()),
+                            &group.buffer,
+                            0..group.instances.len() as u32,
+                        ),
+                        None => continue,
+                    },
+                    InstanceSource::Frame(path, range) => {
+                        (path.as_deref(), &self.frame_instance_buffer, range.clone())
+                    }
+                };
+                render_pass.set_bind_group(1, self.texture_bind_group(texture_path), &[]);
+                render_pass.set_vertex_buffer(1, buffer.slice(..));
+                render_pass.draw_indexed(0..self.num_indices, 0, instances);
             }
         }
+        self.scene_cache.record_frame(culled, draws.len());
 
         // Update and render overlay
+        self.update_selection_edges();
+        self.update_screen_labels();
         self.overlay_renderer.update(
             &self.device,
             &self.queue,
-            self.overlay_data.fps,
-            self.overlay_data.player_position,
+            &self.overlay_data,
             self.size.width as f32,
             self.size.height as f32,
         );
@@ -662,7 +1353,15 @@ impl Renderer {
                     },
                     depth_slice: None,
                 })],
-                depth_stencil_attachment: None,
+                // Kept from the scene pass; the overlay ignores it (always passes)
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                    stencil_ops: None,
+                }),
                 occlusion_query_set: None,
                 timestamp_writes: None,
             });
@@ -680,11 +1379,182 @@ impl Renderer {
         self.blocks_to_render = blocks;
     }
 
+    pub fn set_entities_to_render(&mut self, entities: Vec<EntityInstance>) {
+        self.entities_to_render = entities;
+    }
+
+    /// Background color used to clear the scene pass, applied from the next frame
+    pub fn set_clear_color(&mut self, color: wgpu::Color) {
+        self.clear_color = color;
+    }
+
+    pub fn clear_color(&self) -> wgpu::Color {
+        self.clear_color
+    }
+
+    /// Replace the block type -> tint table. Unknown types render untinted (white).
+    pub fn set_block_colors(&mut self, colors: ahash::AHashMap<String, [f32; 4]>) {
+        self.block_colors = colors;
+        // Tints are baked into the cached instance buffers
+        self.scene_cache.clear();
+    }
+
+    /// Replace the faction id -> tint table. Blocks without a faction, or with an
+    /// unknown one, keep the neutral (white) tint.
+    pub fn set_faction_colors(&mut self, colors: ahash::AHashMap<u32, [f32; 4]>) {
+        self.faction_colors = colors;
+        self.scene_cache.clear();
+    }
+
+    /// Block type tint modulated by the owner faction's color
+    fn block_color(&self, block: &BlockInstance) -> [f32; 4] {
+        let neutral = [1.0, 1.0, 1.0, 1.0];
+        let base = self
+            .block_colors
+            .get(&block.block_type)
+            .copied()
+            .unwrap_or(neutral);
+        let faction = block
+            .faction_id
+            .and_then(|id| self.faction_colors.get(&id).copied())
+            .unwrap_or(neutral);
+        std::array::from_fn(|i| base[i] * faction[i])
+    }
+
     pub fn update_overlay_data(&mut self, fps: f32, player_position: Option<Vec3>) {
         self.overlay_data.fps = fps;
         self.overlay_data.player_position = player_position;
     }
 
+    /// Projects a world position to window pixels using the last uploaded camera.
+    /// Coordinates are physical pixels (same space as the overlay), so HiDPI scaling is included.
+    pub fn world_to_screen(&self, pos: Vec3) -> Option<(f32, f32)> {
+        let view_proj = Mat4::from_cols_array_2d(&self.camera_uniform.view_proj);
+        project_to_screen(
+            view_proj,
+            pos,
+            self.size.width as f32,
+            self.size.height as f32,
+        )
+    }
+
+    /// Block (by center position) outlined in the overlay, `None` to hide the outline
+    pub fn set_selection(&mut self, selection: Option<Vec3>) {
+        self.selection = selection;
+        self.overlay_data.target_block = selection;
+    }
+
+    /// Nearest rendered block hit by a ray, as its center position
+    pub fn pick_block(&self, origin: Vec3, dir: Vec3, max_distance: f32) -> Option<Vec3> {
+        let inv_dir = dir.normalize_or_zero().recip();
+        self.blocks_to_render
+            .iter()
+            .filter_map(|block| {
+                // Slab test against the block's unit cube, axis-aligned (exact
+                // for blocks turned by multiples of 90°)
+                let position = block.position();
+                let t1 = (position - Vec3::splat(0.5) - origin) * inv_dir;
+                let t2 = (position + Vec3::splat(0.5) - origin) * inv_dir;
+                let t_near = t1.min(t2).max_element();
+                let t_far = t1.max(t2).min_element();
+                (t_near <= t_far && t_far >= 0.0 && t_near <= max_distance)
+                    .then_some((t_near.max(0.0), position))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, position)| position)
+    }
+
+    /// World-anchored text (names, block info) drawn by the overlay each frame
+    pub fn set_labels(&mut self, labels: &[(Vec3, String)]) {
+        self.labels = labels.to_vec();
+    }
+
+    /// Projects labels to the screen, dropping those too far, off-screen or
+    /// behind geometry (one `pick_block` ray per label)
+    fn update_screen_labels(&mut self) {
+        let view_proj = Mat4::from_cols_array_2d(&self.camera_uniform.view_proj);
+        let eye = Vec3::from(self.camera_uniform.view_pos);
+        let (w, h) = (self.size.width as f32, self.size.height as f32);
+        self.overlay_data.labels = self
+            .labels
+            .iter()
+            .filter_map(|(pos, text)| {
+                let distance = pos.distance(eye);
+                if distance > LABEL_MAX_DISTANCE {
+                    return None;
+                }
+                // Behind the camera or off-screen: not drawn
+                let (x, y) = project_to_screen(view_proj, *pos, w, h)?;
+                // Hidden behind a block; the block the label sits in does not count
+                let hit = self.pick_block(eye, *pos - eye, distance);
+                if hit.is_some_and(|center| (*pos - center).abs().max_element() > BLOCK_HALF_EXTENT)
+                {
+                    return None;
+                }
+                let fade = (distance - LABEL_FADE_START) / (LABEL_MAX_DISTANCE - LABEL_FADE_START);
+                Some(ScreenLabel {
+                    x,
+                    y,
+                    text: text.clone(),
+                    alpha: 1.0 - fade.clamp(0.0, 1.0),
+                })
+            })
+            .collect();
+    }
+
+    fn update_selection_edges(&mut self) {
+        self.overlay_data.selection_edges.clear();
+        let Some(center) = self.selection else {
+            return;
+        };
+        // Slightly larger than the block to stay visible on its faces
+        let h = 0.52;
+        let corner = |i: usize| {
+            center
+                + Vec3::new(
+                    if i & 1 == 0 { -h } else { h },
+                    if i & 2 == 0 { -h } else { h },
+                    if i & 4 == 0 { -h } else { h },
+                )
+        };
+        let view_proj = Mat4::from_cols_array_2d(&self.camera_uniform.view_proj);
+        let (w, hgt) = (self.size.width as f32, self.size.height as f32);
+        for a in 0..8 {
+            for bit in [1, 2, 4] {
+                let b = a | bit;
+                if b == a {
+                    continue;
+                }
+                if let (Some(p), Some(q)) = (
+                    project_to_pixels(view_proj, corner(a), w, hgt),
+                    project_to_pixels(view_proj, corner(b), w, hgt),
+                ) {
+                    self.overlay_data.selection_edges.push([p, q]);
+                }
+            }
+        }
+    }
+
+    pub fn toggle_frame_graph(&mut self) {
+        self.overlay_data.show_frame_graph = !self.overlay_data.show_frame_graph;
+    }
+
+    /// Shows or hides the FPS / position / target panel
+    pub fn toggle_stats(&mut self) {
+        self.overlay_data.show_stats = !self.overlay_data.show_stats;
+    }
+
+    fn record_frame_time(&mut self) {
+        let now = std::time::Instant::now();
+        if let Some(last) = self.last_frame_instant.replace(now) {
+            let times = &mut self.overlay_data.frame_times;
+            if times.len() == FRAME_HISTORY_LEN {
+                times.pop_front();
+            }
+            times.push_back((now - last).as_secs_f32() * 1000.0);
+        }
+    }
+
     pub fn update_camera(&mut self, camera: &Camera) {
         // Update camera uniform with FPS camera data
         self.camera_uniform.view_proj = camera.view_projection_matrix().to_cols_array_2d();
@@ -698,16 +1568,58 @@ impl Renderer {
         );
     }
 
+    /// Enables or disables frustum culling of blocks (on by default)
+    pub fn set_frustum_culling(&mut self, enabled: bool) {
+        self.frustum_culling = enabled;
+    }
+
+    pub fn frustum_culling(&self) -> bool {
+        self.frustum_culling
+    }
+
     pub fn get_cache_stats(&self) -> scene_cache::CacheStats {
         self.scene_cache.stats()
     }
 }
 
+/// Clip-space projection to pixel coordinates (origin top-left).
+/// Returns `None` behind the camera or outside the viewport.
+pub fn project_to_screen(
+    view_proj: Mat4,
+    pos: Vec3,
+    width: f32,
+    height: f32,
+) -> Option<(f32, f32)> {
+    let clip = view_proj * pos.extend(1.0);
+    let ndc = clip.truncate() / clip.w;
+    if ndc.x.abs() > 1.0 || ndc.y.abs() > 1.0 || ndc.z < 0.0 || ndc.z > 1.0 {
+        return None;
+    }
+    project_to_pixels(view_proj, pos, width, height)
+}
+
+/// Same as `project_to_screen` but only rejects points behind the camera,
+/// so shapes crossing the viewport border keep their off-screen vertices
+fn project_to_pixels(view_proj: Mat4, pos: Vec3, width: f32, height: f32) -> Option<(f32, f32)> {
+    let clip = view_proj * pos.extend(1.0);
+    if clip.w <= 0.0 {
+        return None;
+    }
+    let ndc = clip.truncate() / clip.w;
+    Some(((ndc.x + 1.0) * 0.5 * width, (1.0 - ndc.y) * 0.5 * height))
+}
+
 pub struct Camera {
     pub position: glam::Vec3,
     pub yaw: f32,
     pub pitch: f32,
+    /// Current (animated) field of view, in radians
     pub fov: f32,
+    /// Field of view at rest, in radians
+    pub base_fov: f32,
+    /// FOV multipliers applied while sprinting / zooming
+    pub sprint_fov_scale: f32,
+    pub zoom_fov_scale: f32,
     pub aspect: f32,
     pub near: f32,
     pub far: f32,
@@ -720,6 +1632,9 @@ impl Camera {
             yaw: 0.0,
             pitch: 0.0,
             fov: 45.0_f32.to_radians(),
+            base_fov: 45.0_f32.to_radians(),
+            sprint_fov_scale: 1.15,
+            zoom_fov_scale: 0.4,
             aspect: width / height,
             near: 0.1,
             far: 100.0,
@@ -730,17 +1645,44 @@ impl Camera {
         self.aspect = width / height;
     }
 
-    pub fn view_matrix(&self) -> glam::Mat4 {
-        let forward = glam::Vec3::new(
+    /// Smoothly moves the FOV toward `base_fov` scaled by the sprint/zoom state
+    pub fn update_fov(&mut self, sprinting: bool, zooming: bool, dt: f32) {
+        let mut target = self.base_fov;
+        if sprinting {
+            target *= self.sprint_fov_scale;
+        }
+        if zooming {
+            target *= self.zoom_fov_scale;
+        }
+        let target = target.clamp(10.0_f32.to_radians(), 120.0_f32.to_radians());
+        self.fov += (target - self.fov) * (1.0 - (-10.0 * dt).exp());
+    }
+
+    /// Render distance = far plane; takes effect on the next `update_camera`
+    pub fn set_render_distance(&mut self, distance: f32) {
+        self.far = distance.clamp(self.near * 10.0, 100_000.0);
+    }
+
+    /// Sets both clip planes. A larger near plane keeps more depth precision
+    /// for distant geometry; far is kept at least 10x near.
+    pub fn set_clip_planes(&mut self, near: f32, far: f32) {
+        self.near = near.clamp(0.01, 10.0);
+        self.set_render_distance(far);
+    }
+
+    pub fn forward(&self) -> glam::Vec3 {
+        glam::Vec3::new(
             self.yaw.cos() * self.pitch.cos(),
             self.pitch.sin(),
             self.yaw.sin() * self.pitch.cos(),
         )
-        .normalize();
+        .normalize()
+    }
 
+    pub fn view_matrix(&self) -> glam::Mat4 {
         let up = glam::Vec3::Y;
 
-        glam::Mat4::look_at_rh(self.position, self.position + forward, up)
+        glam::Mat4::look_at_rh(self.position, self.position + self.forward(), up)
     }
 
     pub fn projection_matrix(&self) -> glam::Mat4 {
@@ -751,9 +1693,17 @@ impl Camera {
         self.projection_matrix() * self.view_matrix()
     }
 
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_view_proj(self.view_projection_matrix())
+    }
+
     pub fn position(&self) -> glam::Vec3 {
         self.position
     }
+
+    pub fn world_to_screen(&self, pos: Vec3, width: f32, height: f32) -> Option<(f32, f32)> {
+        project_to_screen(self.view_projection_matrix(), pos, width, height)
+    }
 }
 
 pub struct InputHandler {
@@ -788,15 +1738,14 @@ impl InputHandler {
 
     pub fn update_camera(&mut self, camera: &mut Camera, dt: f32) {
         let base_speed = 5.0;
-        let sprint_multiplier = if self.keys_pressed.contains(&KeyCode::ShiftLeft)
-            || self.keys_pressed.contains(&KeyCode::ShiftRight)
-        {
-            2.5
-        } else {
-            1.0
-        };
+        let sprinting = self.keys_pressed.contains(&KeyCode::ShiftLeft)
+            || self.keys_pressed.contains(&KeyCode::ShiftRight);
+        let sprint_multiplier = if sprinting { 2.5 } else { 1.0 };
         let speed = base_speed * sprint_multiplier * dt;
 
+        // Dynamic FOV: widen while sprinting, narrow while zooming
+        camera.update_fov(sprinting, self.keys_pressed.contains(&KeyCode::KeyZ), dt);
+
         // Mouse look
         camera.yaw += self.mouse_delta.0 * self.mouse_sensitivity;
         camera.pitch -= self.mouse_delta.1 * self.mouse_sensitivity;
@@ -834,3 +1783,261 @@ impl InputHandler {
         }
     }
 }
+
+impl Default for InputHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(id: u32, position: Vec3, texture_path: &str) -> BlockInstance {
+        BlockInstance {
+            id,
+            version: 1,
+            transform: Mat4::from_translation(position),
+            block_type: "armor".to_string(),
+            texture_path: texture_path.to_string(),
+            faction_id: None,
+        }
+    }
+
+    fn instance(position: Vec3) -> InstanceRaw {
+        InstanceRaw {
+            model: Mat4::from_translation(position).to_cols_array_2d(),
+            color: [1.0; 4],
+        }
+    }
+
+    /// Instances of each texture group, as `update_instance_groups` caches them
+    fn grouped_instances(blocks: &[BlockInstance]) -> Vec<(&str, Vec<InstanceRaw>)> {
+        group_by_texture(blocks)
+            .into_iter()
+            .map(|(path, blocks)| {
+                let instances = blocks.iter().map(|b| instance(b.position())).collect();
+                (path, instances)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn draw_calls_drop_to_one_per_texture() {
+        let textures = ["stone.png", "metal.png", "glass.png"];
+        let blocks: Vec<BlockInstance> = (0..30)
+            .map(|i| block(i, Vec3::new(i as f32, 0.0, 0.0), textures[i as usize % 3]))
+            .collect();
+
+        let groups = grouped_instances(&blocks);
+        let (frame_instances, draws, culled) = plan_draws(
+            Vec::new(),
+            groups.iter().map(|(path, i)| (*path, i.as_slice())),
+            None,
+        );
+        assert!(frame_instances.is_empty());
+        assert_eq!(culled, 0);
+
+        // One draw per block before instancing, one per distinct texture now
+        let mut cache = SceneCache::new();
+        cache.record_frame(culled, draws.len());
+        assert_eq!(cache.stats().draw_calls_last_frame, textures.len());
+        assert!(draws.len() < blocks.len());
+    }
+
+    #[test]
+    fn entities_add_a_single_draw() {
+        let blocks = vec![block(1, Vec3::ZERO, "a.png"), block(2, Vec3::X, "a.png")];
+        let groups = grouped_instances(&blocks);
+        let entities = vec![instance(Vec3::Y), instance(Vec3::Z)];
+        let (frame_instances, draws, _) = plan_draws(
+            entities,
+            groups.iter().map(|(path, i)| (*path, i.as_slice())),
+            None,
+        );
+        assert_eq!(frame_instances.len(), 2);
+        assert_eq!(draws.len(), 2);
+        assert!(matches!(&draws[0], InstanceSource::Frame(None, range) if *range == (0..2)));
+    }
+
+    fn view_proj(eye: Vec3, target: Vec3) -> Mat4 {
+        Mat4::perspective_rh(60f32.to_radians(), 16.0 / 9.0, 0.1, 1000.0)
+            * Mat4::look_at_rh(eye, target, Vec3::Y)
+    }
+
+    #[test]
+    fn camera_looking_away_culls_block() {
+        let block = Aabb::from_center_half_extents(
+            Vec3::new(0.0, 0.0, -10.0),
+            Vec3::splat(BLOCK_HALF_EXTENT),
+        );
+        let facing = Frustum::from_view_proj(view_proj(Vec3::ZERO, Vec3::NEG_Z));
+        let away = Frustum::from_view_proj(view_proj(Vec3::ZERO, Vec3::Z));
+        assert!(facing.intersects_aabb(&block));
+        assert!(!away.intersects_aabb(&block));
+
+        // Same block through the draw planner: the group is skipped entirely
+        let instances = [instance(block.center())];
+        let groups = || std::iter::once(("a.png", &instances[..]));
+        let (_, draws, culled) = plan_draws(Vec::new(), groups(), Some(&away));
+        assert!(draws.is_empty());
+        assert_eq!(culled, 1);
+        let (_, draws, culled) = plan_draws(Vec::new(), groups(), Some(&facing));
+        assert_eq!(draws.len(), 1);
+        assert_eq!(culled, 0);
+    }
+
+    #[test]
+    fn block_at_screen_edge_stays_visible_while_panning() {
+        // Block centred on camera A's left edge, 20 units out; camera B has
+        // turned 3° to the right, which leaves it about 1 unit past B's left plane
+        let half_fov_x = (30f32.to_radians().tan() * 16.0 / 9.0).atan();
+        let edge = glam::Quat::from_rotation_y(half_fov_x) * Vec3::NEG_Z;
+        let instances = [instance(edge * 20.0)];
+        let panned = glam::Quat::from_rotation_y(-3f32.to_radians()) * Vec3::NEG_Z;
+        let cameras = [
+            view_proj(Vec3::ZERO, Vec3::NEG_Z),
+            view_proj(Vec3::ZERO, panned),
+        ];
+
+        let culled = |frustum: &Frustum| {
+            let groups = std::iter::once(("a.png", &instances[..]));
+            plan_draws(Vec::new(), groups, Some(frustum)).2
+        };
+        // Exact planes: visible from A, culled from B
+        assert_eq!(culled(&Frustum::from_view_proj(cameras[0])), 0);
+        assert_eq!(culled(&Frustum::from_view_proj(cameras[1])), 1);
+        // With the margin it is kept on both frames
+        for camera in cameras {
+            assert_eq!(culled(&culling_frustum(camera)), 0);
+        }
+    }
+
+    #[test]
+    fn partly_culled_group_draws_visible_blocks_from_frame_buffer() {
+        let facing = Frustum::from_view_proj(view_proj(Vec3::ZERO, Vec3::NEG_Z));
+        let instances = [
+            instance(Vec3::new(0.0, 0.0, -10.0)),
+            instance(Vec3::new(0.0, 0.0, 10.0)),
+        ];
+        let (frame_instances, draws, culled) = plan_draws(
+            Vec::new(),
+            std::iter::once(("a.png", &instances[..])),
+            Some(&facing),
+        );
+        assert_eq!(culled, 1);
+        assert_eq!(frame_instances.len(), 1);
+        assert!(
+            matches!(&draws[..], [InstanceSource::Frame(Some(path), range)] if path == "a.png" && *range == (0..1))
+        );
+    }
+
+    #[test]
+    fn project_to_screen_centre() {
+        let vp = view_proj(Vec3::ZERO, Vec3::NEG_Z);
+        let (x, y) = project_to_screen(vp, Vec3::new(0.0, 0.0, -10.0), 1280.0, 720.0).unwrap();
+        assert!((x - 640.0).abs() < 1e-3 && (y - 360.0).abs() < 1e-3);
+
+        // Up in the world is up on screen, i.e. a smaller pixel y
+        let (_, y_up) = project_to_screen(vp, Vec3::new(0.0, 1.0, -10.0), 1280.0, 720.0).unwrap();
+        assert!(y_up < 360.0);
+    }
+
+    #[test]
+    fn project_to_screen_rejects_points_behind_camera() {
+        let vp = view_proj(Vec3::ZERO, Vec3::NEG_Z);
+        assert_eq!(
+            project_to_screen(vp, Vec3::new(0.0, 0.0, 10.0), 1280.0, 720.0),
+            None
+        );
+        assert_eq!(
+            project_to_screen(vp, Vec3::new(0.5, 0.2, 1.0), 1280.0, 720.0),
+            None
+        );
+        assert_eq!(
+            project_to_pixels(vp, Vec3::new(0.0, 0.0, 10.0), 1280.0, 720.0),
+            None
+        );
+    }
+
+    #[test]
+    fn project_to_screen_rejects_off_screen_points() {
+        let vp = view_proj(Vec3::ZERO, Vec3::NEG_Z);
+        for pos in [
+            Vec3::new(100.0, 0.0, -10.0),
+            Vec3::new(0.0, -100.0, -10.0),
+            Vec3::new(0.0, 0.0, -5000.0),
+        ] {
+            assert_eq!(project_to_screen(vp, pos, 1280.0, 720.0), None, "{pos}");
+        }
+        // Edges of selection outlines keep their off-screen vertices
+        let (x, _) = project_to_pixels(vp, Vec3::new(100.0, 0.0, -10.0), 1280.0, 720.0).unwrap();
+        assert!(x > 1280.0);
+    }
+
+    #[test]
+    fn mip_chain_halves_down_to_one_pixel() {
+        assert_eq!(mip_sizes(1, 1), vec![(1, 1)]);
+        assert_eq!(
+            mip_sizes(16, 16),
+            vec![(16, 16), (8, 8), (4, 4), (2, 2), (1, 1)]
+        );
+        // Non-square: the short side stays at 1 while the long one keeps halving
+        assert_eq!(mip_sizes(8, 2), vec![(8, 2), (4, 1), (2, 1), (1, 1)]);
+        // Odd sizes round down
+        assert_eq!(mip_sizes(5, 3), vec![(5, 3), (2, 1), (1, 1)]);
+        assert_eq!(mip_sizes(1024, 512).len(), 11);
+    }
+
+    #[test]
+    fn upload_budget_counts_instances() {
+        assert!(fits_upload_budget(0, 100, 256));
+        assert!(fits_upload_budget(100, 156, 256));
+        assert!(!fits_upload_budget(100, 157, 256));
+        // A group larger than the whole budget still goes through, alone
+        assert!(fits_upload_budget(0, 10_000, 256));
+        assert!(!fits_upload_budget(1, 10_000, 256));
+    }
+
+    #[test]
+    fn impossible_adapter_request_returns_no_adapter() {
+        // No backend to search and a name nothing matches: no surface needed
+        let backends = wgpu::Backends::empty();
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends,
+            ..Default::default()
+        });
+        let config = RendererConfig {
+            backends: Some(backends),
+            force_adapter_name: Some("no such gpu".into()),
+            ..Default::default()
+        };
+        let result = pollster::block_on(select_adapter(&instance, backends, &config, None));
+        assert!(matches!(result, Err(RendererError::NoAdapter(_))));
+    }
+
+    #[test]
+    fn texture_cache_loads_each_path_once() {
+        let fixture = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../game_client/assets/textures/large_grids/light_armor_block.png"
+        );
+        let missing = "assets/textures/large_grids/no_such_block.png";
+        let mut cache = ahash::AHashMap::new();
+        let mut loads = Vec::new();
+        let mut load = |path: &str| {
+            loads.push(path.to_string());
+            image::open(path).map(|img| img.to_rgba8().dimensions())
+        };
+
+        for _ in 0..2 {
+            let paths = [fixture, missing, fixture].map(str::to_string);
+            fill_texture_cache(&mut cache, paths, &mut load);
+        }
+        // Decoded once, then reused; the missing file is not retried either
+        assert_eq!(loads, [fixture, missing]);
+        assert!(matches!(cache[fixture], Some((w, h)) if w > 0 && h > 0));
+        assert_eq!(cache[missing], None);
+    }
+}
@@ -1,4 +1,4 @@
-use glam::Vec3;
+use crate::OverlayData;
 use wgpu::util::DeviceExt;
 
 /// Simple overlay renderer for displaying text and info on screen
@@ -78,7 +78,14 @@ impl OverlayRenderer {
                 unclipped_depth: false,
                 conservative: false,
             },
-            depth_stencil: None,
+            // Drawn over the scene: shares its depth buffer but never tests or writes it
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: crate::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
             multisample: wgpu::MultisampleState {
                 count: 1,
                 mask: !0,
@@ -108,154 +115,248 @@ impl OverlayRenderer {
         &mut self,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
-        fps: f32,
-        position: Option<Vec3>,
+        data: &OverlayData,
         screen_width: f32,
         screen_height: f32,
     ) {
+        let fps = data.fps;
+        let position = data.player_position;
         let mut vertices = Vec::new();
 
         // Background panel (top-left corner)
         let panel_width = 300.0;
-        let panel_height = 100.0;
+        let panel_height = 125.0;
         let panel_x = 10.0;
         let panel_y = 10.0;
 
-        self.add_rect(
-            &mut vertices,
-            panel_x,
-            panel_y,
-            panel_width,
-            panel_height,
-            [0.1, 0.1, 0.1, 0.8],
-            screen_width,
-            screen_height,
-        );
-
-        // FPS text simulation with colored bars
-        let text_x = panel_x + 10.0;
-        let text_y = panel_y + 20.0;
-
-        // "FPS:" label
-        self.add_text(
-            &mut vertices,
-            text_x,
-            text_y,
-            "FPS:",
-            [1.0, 1.0, 1.0, 1.0],
-            screen_width,
-            screen_height,
-        );
-
-        // FPS value bar (green if >50, yellow if >30, red otherwise)
-        let fps_color = if fps > 50.0 {
-            [0.0, 1.0, 0.0, 1.0]
-        } else if fps > 30.0 {
-            [1.0, 1.0, 0.0, 1.0]
-        } else {
-            [1.0, 0.0, 0.0, 1.0]
-        };
-
-        let fps_bar_width = (fps / 144.0 * 200.0).min(200.0);
-        self.add_rect(
-            &mut vertices,
-            text_x + 60.0,
-            text_y,
-            fps_bar_width,
-            15.0,
-            fps_color,
-            screen_width,
-            screen_height,
-        );
+        if data.show_stats {
+            self.add_rect(
+                &mut vertices,
+                panel_x,
+                panel_y,
+                panel_width,
+                panel_height,
+                [0.1, 0.1, 0.1, 0.8],
+                screen_width,
+                screen_height,
+            );
 
-        // FPS number as simple digit blocks
-        self.add_number(
-            &mut vertices,
-            text_x + 60.0,
-            text_y + 20.0,
-            fps as i32,
-            [1.0, 1.0, 1.0, 1.0],
-            screen_width,
-            screen_height,
-        );
+            // FPS text simulation with colored bars
+            let text_x = panel_x + 10.0;
+            let text_y = panel_y + 20.0;
 
-        // Position
-        if let Some(pos) = position {
-            let pos_y = text_y + 40.0;
+            // "FPS:" label
             self.add_text(
                 &mut vertices,
                 text_x,
-                pos_y,
-                "POS:",
+                text_y,
+                "FPS:",
                 [1.0, 1.0, 1.0, 1.0],
                 screen_width,
                 screen_height,
             );
 
-            // X coordinate (red bar)
-            let x_bar_width = ((pos.x.abs() / 100.0).min(1.0) * 50.0).max(2.0);
+            // FPS value bar (green if >50, yellow if >30, red otherwise)
+            let fps_color = if fps > 50.0 {
+                [0.0, 1.0, 0.0, 1.0]
+            } else if fps > 30.0 {
+                [1.0, 1.0, 0.0, 1.0]
+            } else {
+                [1.0, 0.0, 0.0, 1.0]
+            };
+
+            let fps_bar_width = (fps / 144.0 * 200.0).min(200.0);
             self.add_rect(
                 &mut vertices,
                 text_x + 60.0,
-                pos_y,
-                x_bar_width,
-                12.0,
-                [1.0, 0.3, 0.3, 1.0],
+                text_y,
+                fps_bar_width,
+                15.0,
+                fps_color,
                 screen_width,
                 screen_height,
             );
 
-            // Y coordinate (green bar)
-            let y_bar_width = ((pos.y.abs() / 100.0).min(1.0) * 50.0).max(2.0);
-            self.add_rect(
+            // FPS number as simple digit blocks
+            self.add_number(
                 &mut vertices,
-                text_x + 120.0,
-                pos_y,
-                y_bar_width,
-                12.0,
-                [0.3, 1.0, 0.3, 1.0],
+                text_x + 60.0,
+                text_y + 20.0,
+                fps as i32,
+                [1.0, 1.0, 1.0, 1.0],
                 screen_width,
                 screen_height,
             );
 
-            // Z coordinate (blue bar)
-            let z_bar_width = ((pos.z.abs() / 100.0).min(1.0) * 50.0).max(2.0);
-            self.add_rect(
-                &mut vertices,
-                text_x + 180.0,
-                pos_y,
-                z_bar_width,
-                12.0,
-                [0.3, 0.3, 1.0, 1.0],
-                screen_width,
-                screen_height,
-            );
+            // Position
+            if let Some(pos) = position {
+                let pos_y = text_y + 40.0;
+                self.add_text(
+                    &mut vertices,
+                    text_x,
+                    pos_y,
+                    "POS:",
+                    [1.0, 1.0, 1.0, 1.0],
+                    screen_width,
+                    screen_height,
+                );
+
+                // X coordinate (red bar)
+                let x_bar_width = ((pos.x.abs() / 100.0).min(1.0) * 50.0).max(2.0);
+                self.add_rect(
+                    &mut vertices,
+                    text_x + 60.0,
+                    pos_y,
+                    x_bar_width,
+                    12.0,
+                    [1.0, 0.3, 0.3, 1.0],
+                    screen_width,
+                    screen_height,
+                );
+
+                // Y coordinate (green bar)
+                let y_bar_width = ((pos.y.abs() / 100.0).min(1.0) * 50.0).max(2.0);
+                self.add_rect(
+                    &mut vertices,
+                    text_x + 120.0,
+                    pos_y,
+                    y_bar_width,
+                    12.0,
+                    [0.3, 1.0, 0.3, 1.0],
+                    screen_width,
+                    screen_height,
+                );
+
+                // Z coordinate (blue bar)
+                let z_bar_width = ((pos.z.abs() / 100.0).min(1.0) * 50.0).max(2.0);
+                self.add_rect(
+                    &mut vertices,
+                    text_x + 180.0,
+                    pos_y,
+                    z_bar_width,
+                    12.0,
+                    [0.3, 0.3, 1.0, 1.0],
+                    screen_width,
+                    screen_height,
+                );
+
+                // Numbers below
+                self.add_number(
+                    &mut vertices,
+                    text_x + 60.0,
+                    pos_y + 15.0,
+                    pos.x as i32,
+                    [1.0, 0.3, 0.3, 1.0],
+                    screen_width,
+                    screen_height,
+                );
+                self.add_number(
+                    &mut vertices,
+                    text_x + 120.0,
+                    pos_y + 15.0,
+                    pos.y as i32,
+                    [0.3, 1.0, 0.3, 1.0],
+                    screen_width,
+                    screen_height,
+                );
+                self.add_number(
+                    &mut vertices,
+                    text_x + 180.0,
+                    pos_y + 15.0,
+                    pos.z as i32,
+                    [0.3, 0.3, 1.0, 1.0],
+                    screen_width,
+                    screen_height,
+                );
+            }
 
-            // Numbers below
-            self.add_number(
+            // Targeted block
+            if let Some(target) = data.target_block {
+                let target_y = text_y + 75.0;
+                self.add_text(
+                    &mut vertices,
+                    text_x,
+                    target_y,
+                    "TGT:",
+                    [1.0, 0.9, 0.2, 1.0],
+                    screen_width,
+                    screen_height,
+                );
+                for (i, v) in [target.x, target.y, target.z].into_iter().enumerate() {
+                    self.add_number(
+                        &mut vertices,
+                        text_x + 60.0 + i as f32 * 60.0,
+                        target_y,
+                        v.round() as i32,
+                        [1.0, 0.9, 0.2, 1.0],
+                        screen_width,
+                        screen_height,
+                    );
+                }
+            }
+        }
+
+        // Crosshair at screen center
+        let (cx, cy) = (screen_width * 0.5, screen_height * 0.5);
+        let crosshair_color = [1.0, 1.0, 1.0, 0.9];
+        self.add_rect(
+            &mut vertices,
+            cx - 8.0,
+            cy - 1.0,
+            16.0,
+            2.0,
+            crosshair_color,
+            screen_width,
+            screen_height,
+        );
+        self.add_rect(
+            &mut vertices,
+            cx - 1.0,
+            cy - 8.0,
+            2.0,
+            16.0,
+            crosshair_color,
+            screen_width,
+            screen_height,
+        );
+
+        // World labels, centered on their anchor
+        for label in &data.labels {
+            let width = label.text.chars().count() as f32 * 8.0;
+            self.add_text(
                 &mut vertices,
-                text_x + 60.0,
-                pos_y + 15.0,
-                pos.x as i32,
-                [1.0, 0.3, 0.3, 1.0],
+                label.x - width * 0.5,
+                label.y,
+                &label.text,
+                [1.0, 1.0, 1.0, label.alpha],
                 screen_width,
                 screen_height,
             );
-            self.add_number(
+        }
+
+        // Selected block outline
+        for &[a, b] in &data.selection_edges {
+            self.add_line(
                 &mut vertices,
-                text_x + 120.0,
-                pos_y + 15.0,
-                pos.y as i32,
-                [0.3, 1.0, 0.3, 1.0],
+                a,
+                b,
+                [1.0, 0.9, 0.2, 1.0],
                 screen_width,
                 screen_height,
             );
-            self.add_number(
+        }
+
+        if data.show_frame_graph && !data.frame_times.is_empty() {
+            let graph_y = if data.show_stats {
+                panel_y + panel_height + 10.0
+            } else {
+                panel_y
+            };
+            self.add_frame_graph(
                 &mut vertices,
-                text_x + 180.0,
-                pos_y + 15.0,
-                pos.z as i32,
-                [0.3, 0.3, 1.0, 1.0],
+                panel_x,
+                graph_y,
+                data,
                 screen_width,
                 screen_height,
             );
@@ -278,7 +379,103 @@ impl OverlayRenderer {
         }
     }
 
+    /// Frame-time history as bars (60px = 50ms), with the 1% low drawn as a line
+    fn add_frame_graph(
+        &self,
+        vertices: &mut Vec<OverlayVertex>,
+        x: f32,
+        y: f32,
+        data: &OverlayData,
+        screen_width: f32,
+        screen_height: f32,
+    ) {
+        let bar_width = 2.0;
+        let graph_width = bar_width * data.frame_times.len().max(120) as f32;
+        let graph_height = 60.0;
+        let to_height = |ms: f32| (ms / 50.0 * graph_height).min(graph_height);
+
+        self.add_rect(
+            vertices,
+            x,
+            y,
+            graph_width,
+            graph_height,
+            [0.1, 0.1, 0.1, 0.8],
+            screen_width,
+            screen_height,
+        );
+
+        for (i, &ms) in data.frame_times.iter().enumerate() {
+            let color = if ms <= 1000.0 / 60.0 {
+                [0.0, 1.0, 0.0, 1.0]
+            } else if ms <= 1000.0 / 30.0 {
+                [1.0, 1.0, 0.0, 1.0]
+            } else {
+                [1.0, 0.0, 0.0, 1.0]
+            };
+            let h = to_height(ms);
+            self.add_rect(
+                vertices,
+                x + i as f32 * bar_width,
+                y + graph_height - h,
+                bar_width * 0.75,
+                h,
+                color,
+                screen_width,
+                screen_height,
+            );
+        }
+
+        // 1% low: the frame time only exceeded by the worst 1% of frames
+        let mut sorted: Vec<f32> = data.frame_times.iter().copied().collect();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let p99 = sorted[(sorted.len() * 99 / 100).min(sorted.len() - 1)];
+        self.add_rect(
+            vertices,
+            x,
+            y + graph_height - to_height(p99),
+            graph_width,
+            1.0,
+            [1.0, 0.2, 1.0, 1.0],
+            screen_width,
+            screen_height,
+        );
+    }
+
+    /// Add a 2px thick line segment between two pixel positions
+    fn add_line(
+        &self,
+        vertices: &mut Vec<OverlayVertex>,
+        a: (f32, f32),
+        b: (f32, f32),
+        color: [f32; 4],
+        screen_width: f32,
+        screen_height: f32,
+    ) {
+        let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+        let len = (dx * dx + dy * dy).sqrt();
+        if len < 1e-3 {
+            return;
+        }
+        // Half-thickness offset perpendicular to the segment
+        let (ox, oy) = (-dy / len, dx / len);
+        let to_ndc = |x: f32, y: f32| {
+            [
+                (x / screen_width) * 2.0 - 1.0,
+                -((y / screen_height) * 2.0 - 1.0),
+            ]
+        };
+        let p1 = to_ndc(a.0 + ox, a.1 + oy);
+        let p2 = to_ndc(b.0 + ox, b.1 + oy);
+        let p3 = to_ndc(b.0 - ox, b.1 - oy);
+        let p4 = to_ndc(a.0 - ox, a.1 - oy);
+        for position in [p1, p2, p3, p1, p3, p4] {
+            vertices.push(OverlayVertex { position, color });
+        }
+    }
+
     /// Add a rectangle to the vertex list
+    #[allow(clippy::too_many_arguments)]
     fn add_rect(
         &self,
         vertices: &mut Vec<OverlayVertex>,
@@ -326,6 +523,7 @@ impl OverlayRenderer {
     }
 
     /// Add simple text using small rectangles for each character
+    #[allow(clippy::too_many_arguments)]
     fn add_text(
         &self,
         vertices: &mut Vec<OverlayVertex>,
@@ -352,6 +550,7 @@ impl OverlayRenderer {
     }
 
     /// Render a simple character using small rectangles (bitmap style)
+    #[allow(clippy::too_many_arguments)]
     fn add_char(
         &self,
         vertices: &mut Vec<OverlayVertex>,
@@ -362,8 +561,8 @@ impl OverlayRenderer {
         screen_width: f32,
         screen_height: f32,
     ) {
-        // Very simple 5x7 bitmap font patterns
-        let pattern = match ch {
+        // Very simple 4x5 bitmap font patterns (case-insensitive)
+        let pattern = match ch.to_ascii_uppercase() {
             'F' => vec![
                 [1, 1, 1, 1],
                 [1, 0, 0, 0],
@@ -399,6 +598,251 @@ impl OverlayRenderer {
                 [0, 1, 0, 0],
                 [0, 0, 0, 0],
             ],
+            'A' => vec![
+                [0, 1, 1, 0],
+                [1, 0, 0, 1],
+                [1, 1, 1, 1],
+                [1, 0, 0, 1],
+                [1, 0, 0, 1],
+            ],
+            'B' => vec![
+                [1, 1, 1, 0],
+                [1, 0, 0, 1],
+                [1, 1, 1, 0],
+                [1, 0, 0, 1],
+                [1, 1, 1, 0],
+            ],
+            'C' => vec![
+                [0, 1, 1, 1],
+                [1, 0, 0, 0],
+                [1, 0, 0, 0],
+                [1, 0, 0, 0],
+                [0, 1, 1, 1],
+            ],
+            'D' => vec![
+                [1, 1, 1, 0],
+                [1, 0, 0, 1],
+                [1, 0, 0, 1],
+                [1, 0, 0, 1],
+                [1, 1, 1, 0],
+            ],
+            'E' => vec![
+                [1, 1, 1, 1],
+                [1, 0, 0, 0],
+                [1, 1, 1, 0],
+                [1, 0, 0, 0],
+                [1, 1, 1, 1],
+            ],
+            'G' => vec![
+                [0, 1, 1, 1],
+                [1, 0, 0, 0],
+                [1, 0, 1, 1],
+                [1, 0, 0, 1],
+                [0, 1, 1, 1],
+            ],
+            'H' => vec![
+                [1, 0, 0, 1],
+                [1, 0, 0, 1],
+                [1, 1, 1, 1],
+                [1, 0, 0, 1],
+                [1, 0, 0, 1],
+            ],
+            'I' => vec![
+                [1, 1, 1, 0],
+                [0, 1, 0, 0],
+                [0, 1, 0, 0],
+                [0, 1, 0, 0],
+                [1, 1, 1, 0],
+            ],
+            'J' => vec![
+                [0, 1, 1, 1],
+                [0, 0, 0, 1],
+                [0, 0, 0, 1],
+                [1, 0, 0, 1],
+                [0, 1, 1, 0],
+            ],
+            'K' => vec![
+                [1, 0, 0, 1],
+                [1, 0, 1, 0],
+                [1, 1, 0, 0],
+                [1, 0, 1, 0],
+                [1, 0, 0, 1],
+            ],
+            'L' => vec![
+                [1, 0, 0, 0],
+                [1, 0, 0, 0],
+                [1, 0, 0, 0],
+                [1, 0, 0, 0],
+                [1, 1, 1, 1],
+            ],
+            'M' => vec![
+                [1, 0, 0, 1],
+                [1, 1, 1, 1],
+                [1, 1, 1, 1],
+                [1, 0, 0, 1],
+                [1, 0, 0, 1],
+            ],
+            'N' => vec![
+                [1, 0, 0, 1],
+                [1, 1, 0, 1],
+                [1, 0, 1, 1],
+                [1, 0, 0, 1],
+                [1, 0, 0, 1],
+            ],
+            'Q' => vec![
+                [0, 1, 1, 0],
+                [1, 0, 0, 1],
+                [1, 0, 0, 1],
+                [1, 0, 1, 1],
+                [0, 1, 1, 1],
+            ],
+            'R' => vec![
+                [1, 1, 1, 0],
+                [1, 0, 0, 1],
+                [1, 1, 1, 0],
+                [1, 0, 1, 0],
+                [1, 0, 0, 1],
+            ],
+            'T' => vec![
+                [1, 1, 1, 0],
+                [0, 1, 0, 0],
+                [0, 1, 0, 0],
+                [0, 1, 0, 0],
+                [0, 1, 0, 0],
+            ],
+            'U' => vec![
+                [1, 0, 0, 1],
+                [1, 0, 0, 1],
+                [1, 0, 0, 1],
+                [1, 0, 0, 1],
+                [0, 1, 1, 0],
+            ],
+            'V' => vec![
+                [1, 0, 0, 1],
+                [1, 0, 0, 1],
+                [1, 0, 1, 0],
+                [1, 0, 1, 0],
+                [0, 1, 0, 0],
+            ],
+            'W' => vec![
+                [1, 0, 0, 1],
+                [1, 0, 0, 1],
+                [1, 1, 1, 1],
+                [1, 1, 1, 1],
+                [1, 0, 0, 1],
+            ],
+            'X' => vec![
+                [1, 0, 0, 1],
+                [1, 0, 0, 1],
+                [0, 1, 1, 0],
+                [1, 0, 0, 1],
+                [1, 0, 0, 1],
+            ],
+            'Y' => vec![
+                [1, 0, 0, 1],
+                [1, 0, 0, 1],
+                [0, 1, 1, 0],
+                [0, 1, 0, 0],
+                [0, 1, 0, 0],
+            ],
+            'Z' => vec![
+                [1, 1, 1, 1],
+                [0, 0, 0, 1],
+                [0, 1, 1, 0],
+                [1, 0, 0, 0],
+                [1, 1, 1, 1],
+            ],
+            '0' => vec![
+                [0, 1, 1, 0],
+                [1, 0, 1, 1],
+                [1, 1, 0, 1],
+                [1, 0, 0, 1],
+                [0, 1, 1, 0],
+            ],
+            '1' => vec![
+                [0, 1, 0, 0],
+                [1, 1, 0, 0],
+                [0, 1, 0, 0],
+                [0, 1, 0, 0],
+                [1, 1, 1, 0],
+            ],
+            '2' => vec![
+                [1, 1, 1, 0],
+                [0, 0, 0, 1],
+                [0, 1, 1, 0],
+                [1, 0, 0, 0],
+                [1, 1, 1, 1],
+            ],
+            '3' => vec![
+                [1, 1, 1, 0],
+                [0, 0, 0, 1],
+                [0, 1, 1, 0],
+                [0, 0, 0, 1],
+                [1, 1, 1, 0],
+            ],
+            '4' => vec![
+                [1, 0, 0, 1],
+                [1, 0, 0, 1],
+                [1, 1, 1, 1],
+                [0, 0, 0, 1],
+                [0, 0, 0, 1],
+            ],
+            '5' => vec![
+                [1, 1, 1, 1],
+                [1, 0, 0, 0],
+                [1, 1, 1, 0],
+                [0, 0, 0, 1],
+                [1, 1, 1, 0],
+            ],
+            '6' => vec![
+                [0, 1, 1, 1],
+                [1, 0, 0, 0],
+                [1, 1, 1, 0],
+                [1, 0, 0, 1],
+                [0, 1, 1, 0],
+            ],
+            '7' => vec![
+                [1, 1, 1, 1],
+                [0, 0, 0, 1],
+                [0, 0, 1, 0],
+                [0, 1, 0, 0],
+                [0, 1, 0, 0],
+            ],
+            '8' => vec![
+                [0, 1, 1, 0],
+                [1, 0, 0, 1],
+                [0, 1, 1, 0],
+                [1, 0, 0, 1],
+                [0, 1, 1, 0],
+            ],
+            '9' => vec![
+                [0, 1, 1, 0],
+                [1, 0, 0, 1],
+                [0, 1, 1, 1],
+                [0, 0, 0, 1],
+                [1, 1, 1, 0],
+            ],
+            '-' => vec![
+                [0, 0, 0, 0],
+                [0, 0, 0, 0],
+                [1, 1, 1, 1],
+                [0, 0, 0, 0],
+                [0, 0, 0, 0],
+            ],
+            '.' => vec![
+                [0, 0, 0, 0],
+                [0, 0, 0, 0],
+                [0, 0, 0, 0],
+                [0, 0, 0, 0],
+                [0, 1, 0, 0],
+            ],
+            '\'' => vec![
+                [0, 1, 0, 0],
+                [0, 1, 0, 0],
+                [0, 0, 0, 0],
+                [0, 0, 0, 0],
+                [0, 0, 0, 0],
+            ],
             _ => vec![
                 [0, 0, 0, 0],
                 [0, 0, 0, 0],
@@ -427,6 +871,7 @@ impl OverlayRenderer {
     }
 
     /// Render a number using simple digit blocks
+    #[allow(clippy::too_many_arguments)]
     fn add_number(
         &self,
         vertices: &mut Vec<OverlayVertex>,
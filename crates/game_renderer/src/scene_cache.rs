@@ -1,93 +1,127 @@
-use ahash::AHashMap;
-use std::hash::Hash;
-use wgpu::BindGroup;
+use crate::InstanceRaw;
+use ahash::{AHashMap, AHashSet};
 
-/// Identifiant unique pour un objet rendu
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub struct ObjectId {
-    pub id: u32,
-    pub version: u64,
+/// Blocs partageant une texture, dessinés en un seul appel instancié
+pub struct InstanceGroup {
+    /// (id, version) triés des blocs du groupe : change dès qu'un bloc est
+    /// ajouté, retiré ou modifié
+    key: Vec<(u32, u64)>,
+    /// Copie CPU des instances (sert au frustum culling sans relire le GPU)
+    pub instances: Vec<InstanceRaw>,
+    pub buffer: wgpu::Buffer,
 }
 
-/// Cache pour les bind groups et ressources GPU
+/// Cache des buffers d'instances, un par texture
 pub struct SceneCache {
-    /// Cache des bind groups par ObjectId
-    bind_groups: AHashMap<ObjectId, BindGroup>,
-    /// Version actuelle de chaque objet
-    object_versions: AHashMap<u32, u64>,
+    /// Groupes par chemin de texture
+    groups: AHashMap<String, InstanceGroup>,
+    /// Buffers d'instances reconstruits lors de la dernière frame
+    uploads_last_frame: usize,
+    /// Instances contenues dans ces buffers (ce que limite le budget)
+    instances_uploaded_last_frame: usize,
+    /// Groupes modifiés en attente de reconstruction (budget dépassé)
+    pending_uploads: usize,
+    /// Blocs hors du frustum ignorés lors de la dernière frame
+    culled_last_frame: usize,
+    /// Appels `draw_indexed` de la dernière frame (blocs et entités)
+    draw_calls_last_frame: usize,
 }
 
 impl SceneCache {
     pub fn new() -> Self {
         Self {
-            bind_groups: AHashMap::new(),
-            object_versions: AHashMap::new(),
+            groups: AHashMap::new(),
+            uploads_last_frame: 0,
+            instances_uploaded_last_frame: 0,
+            pending_uploads: 0,
+            culled_last_frame: 0,
+            draw_calls_last_frame: 0,
         }
     }
 
-    /// Vérifie si un objet a été modifié
-    pub fn is_dirty(&self, object_id: u32, version: u64) -> bool {
-        match self.object_versions.get(&object_id) {
-            Some(&cached_version) => cached_version != version,
-            None => true, // Nouvel objet
+    /// Vérifie si le contenu d'un groupe a changé depuis sa dernière reconstruction
+    pub fn is_dirty(&self, texture_path: &str, key: &[(u32, u64)]) -> bool {
+        match self.groups.get(texture_path) {
+            Some(group) => group.key != key,
+            None => true, // Nouveau groupe
         }
     }
 
-    /// Met en cache un bind group pour un objet
-    pub fn cache_bind_group(&mut self, object_id: u32, version: u64, bind_group: BindGroup) {
-        let id = ObjectId {
-            id: object_id,
-            version,
-        };
+    /// Met en cache le buffer d'instances d'un groupe (remplace l'ancien)
+    pub fn cache_group(
+        &mut self,
+        texture_path: String,
+        key: Vec<(u32, u64)>,
+        instances: Vec<InstanceRaw>,
+        buffer: wgpu::Buffer,
+    ) {
+        self.groups.insert(
+            texture_path,
+            InstanceGroup {
+                key,
+                instances,
+                buffer,
+            },
+        );
+    }
 
-        // Nettoie l'ancienne version si elle existe
-        if let Some(&old_version) = self.object_versions.get(&object_id) {
-            let old_id = ObjectId {
-                id: object_id,
-                version: old_version,
-            };
-            self.bind_groups.remove(&old_id);
-        }
+    /// Dernier buffer construit pour une texture, éventuellement en retard sur
+    /// la scène si le budget d'upload est atteint
+    pub fn group(&self, texture_path: &str) -> Option<&InstanceGroup> {
+        self.groups.get(texture_path)
+    }
 
-        self.bind_groups.insert(id, bind_group);
-        self.object_versions.insert(object_id, version);
+    pub fn groups(&self) -> impl Iterator<Item = (&str, &InstanceGroup)> {
+        self.groups
+            .iter()
+            .map(|(path, group)| (path.as_str(), group))
     }
 
-    /// Récupère un bind group depuis le cache
-    pub fn get_bind_group(&self, object_id: u32, version: u64) -> Option<&BindGroup> {
-        let id = ObjectId {
-            id: object_id,
-            version,
-        };
-        self.bind_groups.get(&id)
+    /// Supprime les groupes dont plus aucun bloc n'utilise la texture
+    pub fn cleanup_old_entries(&mut self, active_textures: &AHashSet<&str>) {
+        self.groups
+            .retain(|path, _| active_textures.contains(path.as_str()));
     }
 
-    /// Nettoie les entrées obsolètes du cache
-    pub fn cleanup_old_entries(&mut self, active_objects: &[(u32, u64)]) {
-        let active_ids: AHashMap<u32, u64> = active_objects.iter().copied().collect();
+    /// Enregistre le bilan d'upload de la frame
+    pub fn record_uploads(&mut self, uploaded: usize, instances: usize, pending: usize) {
+        self.uploads_last_frame = uploaded;
+        self.instances_uploaded_last_frame = instances;
+        self.pending_uploads = pending;
+    }
 
-        // Supprime les objets qui ne sont plus actifs
-        self.bind_groups.retain(|object_id, _| {
-            active_ids
-                .get(&object_id.id)
-                .map_or(false, |&version| version == object_id.version)
-        });
+    /// Enregistre les blocs écartés par le frustum culling et les appels de dessin
+    pub fn record_frame(&mut self, culled: usize, draw_calls: usize) {
+        self.culled_last_frame = culled;
+        self.draw_calls_last_frame = draw_calls;
+    }
 
-        // Met à jour les versions d'objets
-        self.object_versions = active_ids;
+    /// Vide entièrement le cache (force la reconstruction de tous les groupes)
+    pub fn clear(&mut self) {
+        self.groups.clear();
     }
 
     /// Statistiques du cache
     pub fn stats(&self) -> CacheStats {
         CacheStats {
-            bind_groups_count: self.bind_groups.len(),
-            objects_count: self.object_versions.len(),
+            groups_count: self.groups.len(),
+            instances_count: self.groups.values().map(|g| g.instances.len()).sum(),
+            uploads_last_frame: self.uploads_last_frame,
+            instances_uploaded_last_frame: self.instances_uploaded_last_frame,
+            pending_uploads: self.pending_uploads,
+            culled_last_frame: self.culled_last_frame,
+            draw_calls_last_frame: self.draw_calls_last_frame,
         }
     }
 }
 
 #[derive(Debug)]
 pub struct CacheStats {
-    pub bind_groups_count: usize,
-    pub objects_count: usize,
+    pub groups_count: usize,
+    pub instances_count: usize,
+    pub uploads_last_frame: usize,
+    pub instances_uploaded_last_frame: usize,
+    pub pending_uploads: usize,
+    pub culled_last_frame: usize,
+    pub draw_calls_last_frame: usize,
 }
@@ -7,51 +7,58 @@ struct VertexInput {
     @location(2) tex_coords: vec2<f32>,
 }
 
+// Per-instance data: model matrix columns and tint
+struct InstanceInput {
+    @location(3) model_0: vec4<f32>,
+    @location(4) model_1: vec4<f32>,
+    @location(5) model_2: vec4<f32>,
+    @location(6) model_3: vec4<f32>,
+    @location(7) color: vec4<f32>,
+}
+
 // Vertex output / Fragment input
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) world_position: vec3<f32>,
     @location(1) world_normal: vec3<f32>,
     @location(2) tex_coords: vec2<f32>,
+    @location(3) color: vec4<f32>,
 }
 
 // Camera uniform buffer
 struct Camera {
     view_proj: mat4x4<f32>,
     view_pos: vec3<f32>,
+    brightness: f32,
+    fullbright: u32,
 }
 @group(0) @binding(0)
 var<uniform> camera: Camera;
 
-// Model matrix uniform
-struct Model {
-    matrix: mat4x4<f32>,
-}
-@group(1) @binding(0)
-var<uniform> model: Model;
-
 // Texture and sampler
-@group(2) @binding(0)
+@group(1) @binding(0)
 var t_diffuse: texture_2d<f32>;
-@group(2) @binding(1)
+@group(1) @binding(1)
 var s_diffuse: sampler;
 
 // Vertex shader - transforms 3D positions
 @vertex
-fn vs_main(vertex: VertexInput) -> VertexOutput {
+fn vs_main(vertex: VertexInput, instance: InstanceInput) -> VertexOutput {
     var out: VertexOutput;
+    let model = mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
     
     // Transform position to world space
-    let world_pos = model.matrix * vec4<f32>(vertex.position, 1.0);
+    let world_pos = model * vec4<f32>(vertex.position, 1.0);
     out.world_position = world_pos.xyz;
     
     // Transform to clip space
     out.clip_position = camera.view_proj * world_pos;
     
     // Transform normal to world space
-    out.world_normal = normalize((model.matrix * vec4<f32>(vertex.normal, 0.0)).xyz);
+    out.world_normal = normalize((model * vec4<f32>(vertex.normal, 0.0)).xyz);
     
     out.tex_coords = vertex.tex_coords;
+    out.color = instance.color;
     
     return out;
 }
@@ -59,8 +66,8 @@ fn vs_main(vertex: VertexInput) -> VertexOutput {
 // Fragment shader - basic lighting for Space Engineers blocks
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    // Sample texture
-    let tex_color = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    // Sample texture, tinted by block type
+    let tex_color = textureSample(t_diffuse, s_diffuse, in.tex_coords) * in.color;
     
     // Basic lighting setup
     let light_dir = normalize(vec3<f32>(0.5, 1.0, 0.3)); // Sun direction
@@ -72,8 +79,11 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     let diffuse_strength = max(dot(normal, light_dir), 0.0);
     let diffuse = diffuse_strength * light_color;
     
-    // Apply lighting to texture
-    let final_color = (ambient + diffuse) * tex_color.rgb;
+    // Apply lighting to texture (fullbright shows the unshaded color)
+    var final_color = (ambient + diffuse) * tex_color.rgb;
+    if camera.fullbright != 0u {
+        final_color = tex_color.rgb;
+    }
     
-    return vec4<f32>(final_color, tex_color.a);
+    return vec4<f32>(final_color * camera.brightness, tex_color.a);
 }
\ No newline at end of file
@@ -1,42 +1,469 @@
-use game_core::arenas::with_current;
-use game_core::ids::PhysicalEntityId;
-use game_core::physics::PhysicalEntity;
-use game_core::world::World;
-
-fn main() {
-    let world = World::new(42, "Main".into());
-
-    // Active le monde courant pour ce thread.
-    let _guard = world.scope();
-
-    // Partout dans ce thread: accès sans passer `&mut Arenas`
-    with_current(|lock| {
-        let mut a = lock.write().unwrap();
-        let id = PhysicalEntityId(1);
-        let _ = a.insert_physical_entity(PhysicalEntity::new(
-            id,
-            None,
-            None,
-            None,
-            None,
-            None,
-            None,
-            None,
-            vec![],
-        ));
-        if let Some(pe) = a.physical_entity_mut(id) {
-            pe.compute_and_apply_pending_deltas();
+// Serveur autoritaire : accepte les connexions, applique les actions des joueurs
+// au monde simulé et diffuse son état à fréquence fixe.
+
+use game_core::objects::blocks::Block;
+use game_core::objects::entities::Entity;
+use game_core::objects::grids::{Grid, GridSizeClass};
+use game_core::objects::humanoids::Humanoid;
+use game_core::objects::physics::{FloatPosition, PhysicalObject, Velocity};
+use game_core::objects::utils::ids::EntityId;
+use game_core::objects::world::World;
+use game_protocol::connection::{GameServer, MAX_ACTIONS_PER_TICK};
+use game_protocol::{BlockState, Message, PlayerAction, PlayerState, ShipState, WorldSnapshot};
+use game_protocol::{Orientation, Position, RelOrientation, RelPosition};
+use std::collections::HashMap;
+use std::time::Duration;
+
+const SERVER_ADDR: &str = "127.0.0.1:8080";
+const TICK_RATE_HZ: u64 = 20;
+/// Point d'apparition des nouveaux joueurs
+const SPAWN_POSITION: Position = Position {
+    x: 0.0,
+    y: 0.0,
+    z: 0.0,
+};
+
+/// Joueur connecté : son humanoïde dans le monde et l'état propre au réseau
+struct PlayerEntry {
+    entity: EntityId,
+    health: f32,
+    last_input_seq: u32,
+}
+
+/// Vaisseau : sa grille dans le monde, `version` incrémentée à chaque
+/// changement de blocks
+struct ShipEntry {
+    grid: EntityId,
+    version: u64,
+}
+
+/// État autoritaire du serveur. Le `World` est la seule source des positions ;
+/// les maps relient les ids du protocole aux entités.
+pub struct ServerState {
+    world: World,
+    /// client_id -> joueur (un joueur par connexion)
+    players: HashMap<u32, PlayerEntry>,
+    /// id de vaisseau du protocole -> grille
+    ships: HashMap<u32, ShipEntry>,
+}
+
+impl ServerState {
+    fn new() -> Self {
+        Self {
+            world: World::new(0, "server".into()),
+            players: HashMap::new(),
+            ships: HashMap::new(),
         }
-    });
+    }
+
+    /// Instantané protocole : corps lus dans le dernier `BodiesSnapshot` publié
+    /// (un tick complet), noms et blocks dans les arènes. Une entité pas encore
+    /// publiée est omise.
+    fn snapshot(&self) -> WorldSnapshot {
+        let latest = self.world.latest_snapshot();
+        let bodies: HashMap<EntityId, &PhysicalObject> =
+            latest.bodies.iter().map(|(id, po)| (*id, po)).collect();
+        let a = self.world.arenas.read().unwrap();
+        let players = self
+            .players
+            .iter()
+            .filter_map(|(&client_id, player)| {
+                let body = *bodies.get(&player.entity)?;
+                let h = a.get_entity(player.entity)?;
+                let g = h.read().unwrap();
+                let Entity::Humanoid(ref humanoid) = *g else {
+                    return None;
+                };
+                let state = PlayerState {
+                    name: humanoid.name.clone().unwrap_or_default(),
+                    position: position_of(Some(body)),
+                    health: player.health,
+                    last_input_seq: player.last_input_seq,
+                };
+                Some((client_id, state))
+            })
+            .collect();
+        let ships = self
+            .ships
+            .iter()
+            .filter_map(|(&ship_id, ship)| {
+                let body = *bodies.get(&ship.grid)?;
+                let h = a.get_entity(ship.grid)?;
+                let g = h.read().unwrap();
+                let Entity::Grid(ref grid) = *g else {
+                    return None;
+                };
+                let blocks = grid
+                    .block_ids
+                    .iter()
+                    .filter_map(|&bid| {
+                        let h = a.get_entity(bid)?;
+                        let g = h.read().unwrap();
+                        let Entity::Block(ref block) = *g else {
+                            return None;
+                        };
+                        Some(block_state(block))
+                    })
+                    .collect();
+                let state = ShipState {
+                    name: grid.name.clone().unwrap_or_default(),
+                    position: position_of(Some(body)),
+                    orientation: orientation_of(Some(body)),
+                    blocks,
+                    version: ship.version,
+                    is_large: !matches!(grid.size_class, Some(GridSizeClass::Small)),
+                };
+                Some((ship_id, state))
+            })
+            .collect();
+        WorldSnapshot { players, ships }
+    }
+
+    fn player_position(&self, client_id: u32) -> Option<Position> {
+        let player = self.players.get(&client_id)?;
+        let a = self.world.arenas.read().unwrap();
+        let h = a.get_entity(player.entity)?;
+        let g = h.read().unwrap();
+        Some(position_of(g.physical_object()))
+    }
+}
 
-    // Appels imbriqués/systèmes profonds:
-    run_system(); // utilisera with_current(...)
+fn position_of(po: Option<&PhysicalObject>) -> Position {
+    po.and_then(|po| po.position.as_ref())
+        .map_or(Position::default(), |p| Position::new(p.x, p.y, p.z))
+}
+
+fn orientation_of(po: Option<&PhysicalObject>) -> Orientation {
+    po.and_then(|po| po.orientation.as_ref())
+        .map_or(Orientation::default(), |o| {
+            Orientation::new(o.pitch, o.yaw, o.roll)
+        })
+}
+
+fn block_state(block: &Block) -> BlockState {
+    let (p, o) = (&block.position, &block.orientation);
+    BlockState {
+        id: block.id.0,
+        name: block.def.name.clone(),
+        block_type: block.def.block_type.clone(),
+        position: RelPosition::new(p.x, p.y, p.z),
+        orientation: RelOrientation::new(o.pitch, o.yaw, o.roll),
+        integrity: block.current_integrity,
+        faction_id: Some(block.faction_id.0),
+    }
+}
+
+/// Corps physique immobile à `position`
+fn body_at(position: Position) -> PhysicalObject {
+    PhysicalObject::new(
+        None,
+        Some(FloatPosition::new(position.x, position.y, position.z)),
+        None,
+        Some(Velocity::zero()),
+        None,
+        None,
+        None,
+        None,
+        Vec::new(),
+    )
+}
+
+/// Phase physique du tick : intègre le monde à pas fixe et publie son instantané
+fn phase_physics(state: &mut ServerState, dt: f32) {
+    state.world.step_physics(dt);
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    tracing_subscriber::fmt::init();
+
+    let mut server = GameServer::new(SERVER_ADDR.parse()?).await?;
+    let mut state = ServerState::new();
+    let listener = server
+        .listener
+        .take()
+        .expect("server was bound to an address");
+    let tick_duration = Duration::from_millis(1000 / TICK_RATE_HZ);
+    let mut tick = tokio::time::interval(tick_duration);
+    tracing::info!("Server listening on {}", SERVER_ADDR);
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, addr)) => {
+                    server.register_connection(stream, addr);
+                }
+                Err(e) => tracing::warn!("Accept failed: {}", e),
+            },
+            Some((client_id, message)) = server.message_rx.recv() => {
+                handle_message(&mut server, &mut state, client_id, message);
+            }
+            _ = tick.tick() => {
+                server.reset_action_counts();
+                phase_physics(&mut state, tick_duration.as_secs_f32());
+                server.broadcast_snapshots(&state.snapshot());
+            }
+        }
+    }
 }
 
-fn run_system() {
-    game_core::arenas::with_current(|lock| {
-        let a = lock.read().unwrap();
-        // lecture…
-        let _ = a.physical_entity(game_core::ids::PhysicalEntityId(1));
-    });
+fn handle_message(
+    server: &mut GameServer,
+    state: &mut ServerState,
+    client_id: u32,
+    message: Message,
+) {
+    match message {
+        Message::Connect { player_name } => {
+            // un joueur par connexion : player_id = client_id
+            tracing::info!(client_id, %player_name, "Player joined");
+            let entity = {
+                let _scope = state.world.scope();
+                Humanoid::spawn(Some(player_name), Some(body_at(SPAWN_POSITION)))
+            };
+            // publié sans attendre le tick pour que le Welcome contienne le joueur
+            state.world.publish_snapshot();
+            state.players.insert(
+                client_id,
+                PlayerEntry {
+                    entity,
+                    health: 100.0,
+                    last_input_seq: 0,
+                },
+            );
+            // le premier UpdatePosition est vérifié depuis le spawn, pas cru sur parole
+            server.set_client_position(client_id, SPAWN_POSITION);
+            let _ = server.send_to_client(
+                client_id,
+                Message::Welcome {
+                    player_id: client_id,
+                    world_state: server.snapshot_for(client_id, &state.snapshot()),
+                },
+            );
+        }
+        Message::Disconnect => {
+            if let Some(player) = state.players.remove(&client_id) {
+                let _scope = state.world.scope();
+                Humanoid::remove(player.entity);
+                tracing::info!(client_id, "Player left");
+            }
+            server.disconnect_client(client_id);
+        }
+        Message::PlayerAction { action } => {
+            let count = server.count_action(client_id);
+            if count > MAX_ACTIONS_PER_TICK {
+                // une seule erreur par tick, le reste est ignoré silencieusement
+                if count == MAX_ACTIONS_PER_TICK + 1 {
+                    tracing::warn!(client_id, "Rate limited");
+                    let _ = server.send_to_client(
+                        client_id,
+                        Message::Error {
+                            message: "Too many actions, slow down".into(),
+                        },
+                    );
+                }
+                return;
+            }
+            if let PlayerAction::UpdatePosition { ref position, seq } = action {
+                if !server.accept_position(client_id, position) {
+                    tracing::warn!(client_id, "Rejected impossible move");
+                    // acquitté sans bouger : le client se recale sur la position serveur
+                    if let Some(player) = state.players.get_mut(&client_id) {
+                        player.last_input_seq = seq;
+                    }
+                    let _ = server.send_to_client(
+                        client_id,
+                        Message::Error {
+                            message: "Position update rejected".into(),
+                        },
+                    );
+                    return;
+                }
+            }
+            apply_action(state, client_id, action);
+        }
+        // messages serveur -> client : ignorés
+        _ => {}
+    }
+}
+
+/// Applique l'action d'un joueur au monde (pure, sans réseau)
+pub fn apply_action(state: &mut ServerState, client_id: u32, action: PlayerAction) {
+    match action {
+        PlayerAction::UpdatePosition { position, seq } => {
+            let Some(player) = state.players.get_mut(&client_id) else {
+                return;
+            };
+            player.last_input_seq = seq;
+            let a = state.world.arenas.read().unwrap();
+            if let Some(h) = a.get_entity(player.entity) {
+                let mut g = h.write().unwrap();
+                if let Some(po) = g.physical_object_mut() {
+                    po.position = Some(FloatPosition::new(position.x, position.y, position.z));
+                }
+            }
+        }
+        PlayerAction::SpawnShip => {
+            let Some(position) = state.player_position(client_id) else {
+                return;
+            };
+            let name = {
+                let a = state.world.arenas.read().unwrap();
+                let h = a.get_entity(state.players[&client_id].entity);
+                h.and_then(|h| match *h.read().unwrap() {
+                    Entity::Humanoid(ref humanoid) => humanoid.name.clone(),
+                    _ => None,
+                })
+                .unwrap_or_default()
+            };
+            let grid = {
+                let _scope = state.world.scope();
+                Grid::spawn(
+                    Some(format!("{}'s ship", name)),
+                    Some(body_at(position)),
+                    None,
+                    Some(GridSizeClass::Large),
+                )
+            };
+            let id = state.ships.keys().max().map_or(1, |m| m + 1);
+            state.ships.insert(id, ShipEntry { grid, version: 0 });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    /// Serveur avec un client connecté ; le récepteur voit ce que le serveur lui envoie
+    fn connected() -> (GameServer, ServerState, mpsc::UnboundedReceiver<Message>) {
+        let mut server = GameServer::in_memory();
+        let mut state = ServerState::new();
+        let (tx, rx) = mpsc::unbounded_channel();
+        server.connections.insert(1, tx);
+        let connect = Message::Connect {
+            player_name: "alice".into(),
+        };
+        handle_message(&mut server, &mut state, 1, connect);
+        (server, state, rx)
+    }
+
+    fn exists(state: &ServerState, id: EntityId) -> bool {
+        state.world.arenas.read().unwrap().get_entity(id).is_some()
+    }
+
+    /// Un tick de physique, qui publie l'instantané du monde
+    fn tick(state: &mut ServerState) {
+        phase_physics(state, 1.0 / TICK_RATE_HZ as f32);
+    }
+
+    fn move_to(position: Position, seq: u32) -> Message {
+        Message::PlayerAction {
+            action: PlayerAction::UpdatePosition { position, seq },
+        }
+    }
+
+    #[test]
+    fn connect_seeds_spawn_position() {
+        let (server, state, mut rx) = connected();
+        assert_eq!(server.client_positions.get(&1), Some(&SPAWN_POSITION));
+        let player = &state.snapshot().players[&1];
+        assert_eq!(player.position, SPAWN_POSITION);
+        assert_eq!(player.name, "alice");
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(Message::Welcome { player_id: 1, world_state }) if world_state.players.contains_key(&1)
+        ));
+    }
+
+    #[test]
+    fn accepts_reachable_first_move() {
+        let (mut server, mut state, mut rx) = connected();
+        let _ = rx.try_recv();
+        let target = Position::new(0.5, 0.0, 0.0);
+        handle_message(&mut server, &mut state, 1, move_to(target, 1));
+        // Le déplacement n'est diffusé qu'après le tick suivant
+        assert_eq!(state.snapshot().players[&1].position, SPAWN_POSITION);
+        tick(&mut state);
+        let player = &state.snapshot().players[&1];
+        assert_eq!(player.position, target);
+        assert_eq!(player.last_input_seq, 1);
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn rejects_teleport_as_first_move() {
+        let (mut server, mut state, mut rx) = connected();
+        let _ = rx.try_recv();
+        handle_message(
+            &mut server,
+            &mut state,
+            1,
+            move_to(Position::new(5000.0, 0.0, 0.0), 1),
+        );
+        let player = &state.snapshot().players[&1];
+        assert_eq!(player.position, SPAWN_POSITION);
+        assert_eq!(player.last_input_seq, 1);
+        assert_eq!(server.client_positions.get(&1), Some(&SPAWN_POSITION));
+        assert!(matches!(rx.try_recv(), Ok(Message::Error { .. })));
+    }
+
+    #[test]
+    fn spawn_ship_adds_a_grid_at_the_player() {
+        let (_, mut state, _) = connected();
+        let target = Position::new(0.5, 0.0, 0.0);
+        apply_action(
+            &mut state,
+            1,
+            PlayerAction::UpdatePosition {
+                position: target,
+                seq: 1,
+            },
+        );
+        apply_action(&mut state, 1, PlayerAction::SpawnShip);
+        apply_action(&mut state, 1, PlayerAction::SpawnShip);
+        tick(&mut state);
+
+        let ships = state.snapshot().ships;
+        assert_eq!(ships.len(), 2);
+        let ship = &ships[&1];
+        assert_eq!(ship.name, "alice's ship");
+        assert_eq!(ship.position, target);
+        assert!(ship.is_large);
+        assert!(ships.contains_key(&2));
+
+        // La grille est une entité du monde
+        assert!(exists(&state, state.ships[&1].grid));
+    }
+
+    #[test]
+    fn phase_physics_moves_bodies_in_the_world() {
+        let (_, mut state, _) = connected();
+        apply_action(&mut state, 1, PlayerAction::SpawnShip);
+        {
+            let a = state.world.arenas.read().unwrap();
+            let h = a.get_entity(state.ships[&1].grid).unwrap();
+            let mut g = h.write().unwrap();
+            g.physical_object_mut().unwrap().velocity = Some(Velocity::new(4.0, 0.0, 0.0));
+        }
+
+        // Deux ticks de 0.25 s à 4 u/s
+        phase_physics(&mut state, 0.25);
+        phase_physics(&mut state, 0.25);
+        assert_eq!(state.world.time, 0.5);
+        let snapshot = state.snapshot();
+        assert_eq!(snapshot.ships[&1].position, Position::new(2.0, 0.0, 0.0));
+        // Sans vitesse, le joueur ne bouge pas
+        assert_eq!(snapshot.players[&1].position, SPAWN_POSITION);
+    }
+
+    #[test]
+    fn disconnect_removes_the_humanoid() {
+        let (mut server, mut state, _) = connected();
+        let entity = state.players[&1].entity;
+        handle_message(&mut server, &mut state, 1, Message::Disconnect);
+        assert!(state.snapshot().players.is_empty());
+        assert!(!exists(&state, entity));
+    }
 }